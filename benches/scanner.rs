@@ -0,0 +1,89 @@
+//! Scanner throughput on identifier-heavy code, and keyword classification before and after the byte trie.
+//!
+//! Run with `cargo bench --bench scanner`. A plain timing loop, as the crate has no benchmark dependencies.
+
+use std::{
+    hint::black_box,
+    time::{Duration, Instant},
+};
+
+use lox::{
+    scanner::Scanner,
+    token::{keyword, TokenType},
+};
+
+const LINE: &str = "var totalCount = alpha + beta * gamma; fun forward(andy, classy) { return thisValue or nilly; }\n";
+const ROUNDS: usize = 5;
+
+/// Keyword classification before the trie: the identifier's text matched against every keyword string.
+fn keyword_before(text: &str) -> Option<TokenType> {
+    let token_type = match text {
+        "and" => TokenType::And,
+        "break" => TokenType::Break,
+        "class" => TokenType::Class,
+        "else" => TokenType::Else,
+        "false" => TokenType::False,
+        "for" => TokenType::For,
+        "fun" => TokenType::Fun,
+        "if" => TokenType::If,
+        "nil" => TokenType::Nil,
+        "or" => TokenType::Or,
+        "print" => TokenType::Print,
+        "return" => TokenType::Return,
+        "super" => TokenType::Super,
+        "this" => TokenType::This,
+        "true" => TokenType::True,
+        "var" => TokenType::Var,
+        "while" => TokenType::While,
+        _ => return None,
+    };
+    Some(token_type)
+}
+
+/// Fastest of a few runs of `f`.
+fn best(mut f: impl FnMut()) -> Duration {
+    (0..ROUNDS)
+        .map(|_| {
+            let start = Instant::now();
+            f();
+            start.elapsed()
+        })
+        .min()
+        .unwrap()
+}
+
+fn main() {
+    let source = LINE.repeat(2_000);
+    let mut tokens = 0;
+    let scan = best(|| {
+        let mut scanner = Scanner::new(source.clone());
+        tokens = black_box(scanner.scan_tokens()).len();
+    });
+    println!(
+        "scan:            {:>10.2?}  {} tokens, {:.1} MB/s",
+        scan,
+        tokens,
+        source.len() as f64 / scan.as_secs_f64() / 1e6
+    );
+
+    let words = source
+        .split(|c: char| !c.is_alphanumeric())
+        .filter(|word| !word.is_empty())
+        .collect::<Vec<_>>();
+    let before = best(|| {
+        for _ in 0..20 {
+            for word in &words {
+                black_box(keyword_before(black_box(word)));
+            }
+        }
+    });
+    let after = best(|| {
+        for _ in 0..20 {
+            for word in &words {
+                black_box(keyword(black_box(word.as_bytes())));
+            }
+        }
+    });
+    println!("keywords before: {:>10.2?}  {} words", before, words.len() * 20);
+    println!("keywords after:  {:>10.2?}", after);
+}
@@ -5,17 +5,28 @@ use crate::token::{Literal, Token};
 #[derive(Debug)]
 pub enum Expr {
     Binary(Box<Expr>, Token, Box<Expr>),
-    // Assign(Token, Box<Expr>),
+    Assign(Token, Box<Expr>),
     // Call(Box<Expr>, Token, Vec<Expr>),
     // Get(Box<Expr>, Token),
     Grouping(Box<Expr>),
     Literal(Literal),
-    // Logical(Box<Expr>, Token, Box<Expr>),
+    Logical(Box<Expr>, Token, Box<Expr>),
     // Set(Box<Expr>, Token, Box<Expr>),
     // Super(Token, Token),
     // This(Token),
     Unary(Token, Box<Expr>),
-    // Variable(Token),
+    Variable(Token),
+}
+
+#[derive(Debug)]
+pub enum Stmt {
+    Expression(Expr),
+    Print(Expr),
+    Var(Token, Option<Expr>),
+}
+
+pub fn assign(name: Token, value: Expr) -> Expr {
+    Expr::Assign(name, Box::new(value))
 }
 
 pub fn binary(left: Expr, operator: Token, right: Expr) -> Expr {
@@ -30,15 +41,26 @@ pub fn literal(literal: Literal) -> Expr {
     Expr::Literal(literal)
 }
 
+pub fn logical(left: Expr, operator: Token, right: Expr) -> Expr {
+    Expr::Logical(Box::new(left), operator, Box::new(right))
+}
+
 pub fn unary(operator: Token, right: Expr) -> Expr {
     Expr::Unary(operator, Box::new(right))
 }
 
+pub fn variable(name: Token) -> Expr {
+    Expr::Variable(name)
+}
+
 impl Display for Expr {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
         match self {
             Expr::Binary(left, operator, right) => {
-                write!(f, "({} {} {})", operator.lexeme, left, right)
+                write!(f, "({} {} {})", operator.lexeme(), left, right)
+            }
+            Expr::Logical(left, operator, right) => {
+                write!(f, "({} {} {})", operator.lexeme(), left, right)
             }
             Expr::Grouping(expr) => {
                 write!(f, "(group {})", expr)
@@ -51,7 +73,20 @@ impl Display for Expr {
                 Literal::Nil => write!(f, "nil"),
             },
 
-            Expr::Unary(operator, right) => write!(f, "({} {})", operator.lexeme, right),
+            Expr::Unary(operator, right) => write!(f, "({} {})", operator.lexeme(), right),
+            Expr::Variable(name) => write!(f, "{}", name.lexeme()),
+            Expr::Assign(name, value) => write!(f, "(= {} {})", name.lexeme(), value),
+        }
+    }
+}
+
+impl Display for Stmt {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Stmt::Expression(expr) => write!(f, "(; {})", expr),
+            Stmt::Print(expr) => write!(f, "(print {})", expr),
+            Stmt::Var(name, Some(initializer)) => write!(f, "(var {} = {})", name.lexeme(), initializer),
+            Stmt::Var(name, None) => write!(f, "(var {})", name.lexeme()),
         }
     }
 }
@@ -66,7 +101,7 @@ mod tests {
     fn test_binary_expr() {
         let expr = binary(
             literal(Literal::Number(1.1)),
-            Token::new(TokenType::Minus, "-".into(), None, 1),
+            Token::new(TokenType::Minus, "-", None, 1),
             literal(Literal::Number(2.0)),
         );
         assert_eq!(expr.to_string(), "(- 1.1 2)");
@@ -76,9 +111,41 @@ mod tests {
     fn test_nested_expr() {
         let expr = binary(
             literal(Literal::Number(1.0)),
-            Token::new(TokenType::Minus, "-".into(), None, 1),
+            Token::new(TokenType::Minus, "-", None, 1),
             grouping(literal(Literal::Number(2.0))),
         );
         assert_eq!(expr.to_string(), "(- 1 (group 2))");
     }
+
+    #[test]
+    fn test_logical_expr() {
+        let expr = logical(
+            literal(Literal::Nil),
+            Token::new(TokenType::Or, "or", None, 1),
+            literal(Literal::String("default".into())),
+        );
+        assert_eq!(expr.to_string(), "(or nil default)");
+    }
+
+    #[test]
+    fn test_assign_expr() {
+        let expr = assign(
+            Token::new(TokenType::Identifier, "a", None, 1),
+            variable(Token::new(TokenType::Identifier, "b", None, 1)),
+        );
+        assert_eq!(expr.to_string(), "(= a b)");
+    }
+
+    #[test]
+    fn test_stmt() {
+        let name = Token::new(TokenType::Identifier, "a", None, 1);
+        let statements = [
+            Stmt::Var(name.clone(), Some(literal(Literal::Number(1.0)))),
+            Stmt::Var(name.clone(), None),
+            Stmt::Print(variable(name.clone())),
+            Stmt::Expression(assign(name, literal(Literal::Nil))),
+        ];
+        let printed = statements.iter().map(Stmt::to_string).collect::<Vec<_>>();
+        assert_eq!(printed, ["(var a = 1)", "(var a)", "(print a)", "(; (= a nil))"]);
+    }
 }
@@ -0,0 +1,216 @@
+//! Constructors for building ASTs from Rust instead of source text.
+//!
+//! Unlike the helpers in [`crate::ast`], which take arbitrary tokens, operators here are typed and names
+//! are checked, so a built tree never holds a token the parser wouldn't have put in that position:
+//!
+//! ```
+//! use lox::builder::{binary, number, print, var, variable, BinaryOp};
+//!
+//! // var a = 1; print a + 2;
+//! let program = [
+//!     var("a", Some(number(1.0))),
+//!     print(binary(variable("a"), BinaryOp::Add, number(2.0))),
+//! ];
+//! ```
+
+use std::rc::Rc;
+
+use crate::{
+    ast::{self, Expr, Stmt},
+    token::{Literal, Token, TokenType},
+};
+
+/// Line given to every synthesized token, so runtime errors in built code still render.
+const LINE: usize = 1;
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum BinaryOp {
+    Add,
+    Subtract,
+    Multiply,
+    Divide,
+    Remainder,
+    Equal,
+    NotEqual,
+    Greater,
+    GreaterEqual,
+    Less,
+    LessEqual,
+}
+
+impl BinaryOp {
+    fn token(self) -> Token {
+        let (token_type, lexeme) = match self {
+            BinaryOp::Add => (TokenType::Plus, "+"),
+            BinaryOp::Subtract => (TokenType::Minus, "-"),
+            BinaryOp::Multiply => (TokenType::Star, "*"),
+            BinaryOp::Divide => (TokenType::Slash, "/"),
+            BinaryOp::Remainder => (TokenType::Percent, "%"),
+            BinaryOp::Equal => (TokenType::EqualEqual, "=="),
+            BinaryOp::NotEqual => (TokenType::BangEqual, "!="),
+            BinaryOp::Greater => (TokenType::Greater, ">"),
+            BinaryOp::GreaterEqual => (TokenType::GreaterEqual, ">="),
+            BinaryOp::Less => (TokenType::Less, "<"),
+            BinaryOp::LessEqual => (TokenType::LessEqual, "<="),
+        };
+        Token::new(token_type, lexeme, None, LINE)
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum UnaryOp {
+    Negate,
+    Not,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum LogicalOp {
+    And,
+    Or,
+}
+
+/// Token for a variable name.
+///
+/// # Panics
+///
+/// If `name` isn't a valid identifier, or is a keyword. As in the scanner, an identifier is a letter followed
+/// by letters and digits, Unicode ones included, and `_` isn't allowed.
+pub fn identifier(name: &str) -> Token {
+    let mut chars = name.chars();
+    let valid = chars.next().is_some_and(char::is_alphabetic) && chars.all(char::is_alphanumeric);
+    assert!(valid, "'{}' is not a valid identifier", name);
+    assert!(TokenType::try_from(name).is_err(), "'{}' is a keyword", name);
+
+    Token::new(TokenType::Identifier, name, None, LINE)
+}
+
+pub fn number(value: f64) -> Expr {
+    ast::literal(Literal::Number(value))
+}
+
+pub fn string(value: impl Into<Rc<str>>) -> Expr {
+    ast::literal(Literal::String(value.into()))
+}
+
+pub fn boolean(value: bool) -> Expr {
+    ast::literal(if value { Literal::True } else { Literal::False })
+}
+
+pub fn nil() -> Expr {
+    ast::literal(Literal::Nil)
+}
+
+pub fn variable(name: &str) -> Expr {
+    ast::variable(identifier(name))
+}
+
+pub fn assign(name: &str, value: Expr) -> Expr {
+    ast::assign(identifier(name), value)
+}
+
+pub fn grouping(expr: Expr) -> Expr {
+    ast::grouping(expr)
+}
+
+pub fn binary(left: Expr, operator: BinaryOp, right: Expr) -> Expr {
+    ast::binary(left, operator.token(), right)
+}
+
+pub fn unary(operator: UnaryOp, right: Expr) -> Expr {
+    let operator = match operator {
+        UnaryOp::Negate => Token::new(TokenType::Minus, "-", None, LINE),
+        UnaryOp::Not => Token::new(TokenType::Bang, "!", None, LINE),
+    };
+    ast::unary(operator, right)
+}
+
+pub fn logical(left: Expr, operator: LogicalOp, right: Expr) -> Expr {
+    let operator = match operator {
+        LogicalOp::And => Token::new(TokenType::And, "and", None, LINE),
+        LogicalOp::Or => Token::new(TokenType::Or, "or", None, LINE),
+    };
+    ast::logical(left, operator, right)
+}
+
+pub fn expression(expr: Expr) -> Stmt {
+    Stmt::Expression(expr)
+}
+
+pub fn print(expr: Expr) -> Stmt {
+    Stmt::Print(expr)
+}
+
+pub fn var(name: &str, initializer: Option<Expr>) -> Stmt {
+    Stmt::Var(identifier(name), initializer)
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{interpreter::Interpreter, parser::Parser, scanner::Scanner};
+
+    use super::*;
+
+    #[test]
+    fn test_matches_parser() {
+        let built = assign(
+            "total",
+            logical(
+                binary(
+                    unary(UnaryOp::Negate, number(1.0)),
+                    BinaryOp::Add,
+                    grouping(binary(variable("x"), BinaryOp::Multiply, number(2.0))),
+                ),
+                LogicalOp::Or,
+                unary(UnaryOp::Not, nil()),
+            ),
+        );
+
+        let mut scanner = Scanner::new("total = -1 + (x * 2) or !nil".into());
+        let tokens = scanner.scan_tokens();
+        let parsed = Parser::new(tokens).expression().unwrap();
+        assert_eq!(built.to_string(), parsed.to_string());
+    }
+
+    #[test]
+    fn test_execute() {
+        let program = [
+            var("greeting", Some(string("hello"))),
+            print(binary(variable("greeting"), BinaryOp::Add, string(" world"))),
+            expression(assign("greeting", boolean(true))),
+        ];
+
+        let mut interpreter = Interpreter::default();
+        for stmt in &program {
+            interpreter.execute(stmt).unwrap();
+        }
+        assert_eq!(interpreter.take_stdout(), "hello world\n");
+    }
+
+    #[test]
+    #[should_panic(expected = "'var' is a keyword")]
+    fn test_keyword_name() {
+        variable("var");
+    }
+
+    #[test]
+    #[should_panic(expected = "'1a' is not a valid identifier")]
+    fn test_invalid_name() {
+        var("1a", None);
+    }
+
+    #[test]
+    #[should_panic(expected = "'a_b' is not a valid identifier")]
+    fn test_underscore_name() {
+        variable("a_b");
+    }
+
+    #[test]
+    fn test_unicode_name() {
+        let program = [var("счёт1", Some(number(1.0))), print(variable("счёт1"))];
+        let mut interpreter = Interpreter::default();
+        for stmt in &program {
+            interpreter.execute(stmt).unwrap();
+        }
+        assert_eq!(interpreter.take_stdout(), "1\n");
+    }
+}
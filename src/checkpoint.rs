@@ -0,0 +1,184 @@
+//! Running a program in slices, with the option to save it between slices and resume it elsewhere.
+//!
+//! A slice runs whole top-level statements until it has used up its budget of steps, so it pauses at
+//! a statement boundary and may overshoot the budget by part of one statement. Use
+//! [`crate::interpreter::InterpreterBuilder::max_steps`] for a hard limit.
+
+use std::io::{self, Read, Write};
+
+use crate::{ast::Stmt, error::Diagnostic, interpreter::Interpreter, parser::Parser, scanner::Scanner, strict};
+
+const HEADER: &str = "lox-checkpoint 1";
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Progress {
+    /// The budget ran out with statements left to run.
+    Paused,
+    Finished,
+}
+
+pub struct Execution {
+    source: String,
+    statements: Vec<Stmt>,
+    /// Index of the next statement to run.
+    next: usize,
+}
+
+impl Execution {
+    /// Parses `source` in the interpreter's dialect, ready to run from the first statement.
+    pub fn new(interpreter: &Interpreter, source: &str) -> Result<Self, Vec<Diagnostic>> {
+        let mut scanner = Scanner::with_dialect(source.into(), interpreter.dialect());
+        scanner.scan_tokens();
+        if !scanner.errors().is_empty() {
+            return Err(scanner.errors().iter().cloned().map(Diagnostic::from).collect());
+        }
+        let program = Parser::new(scanner.tokens()).parse();
+        if !program.errors.is_empty() {
+            return Err(program.errors.into_iter().map(Diagnostic::from).collect());
+        }
+        if interpreter.dialect().strict || strict::has_directive(&program.statements) {
+            strict::check(&program.statements, |name| interpreter.environment().contains(name))
+                .map_err(|err| vec![err.into()])?;
+        }
+
+        Ok(Execution {
+            source: source.into(),
+            statements: program.statements,
+            next: 0,
+        })
+    }
+
+    /// Runs statements until `steps` evaluation steps were used or the program ends.
+    ///
+    /// A statement that fails is not retried: the error is returned and the next call carries on after it.
+    pub fn run(&mut self, interpreter: &mut Interpreter, steps: usize) -> Result<Progress, Diagnostic> {
+        let start = interpreter.steps_executed();
+        while self.next < self.statements.len() && interpreter.steps_executed() - start < steps {
+            let stmt = &self.statements[self.next];
+            self.next += 1;
+            interpreter.execute(stmt)?;
+        }
+
+        Ok(self.progress())
+    }
+
+    pub fn progress(&self) -> Progress {
+        match self.next < self.statements.len() {
+            true => Progress::Paused,
+            false => Progress::Finished,
+        }
+    }
+
+    /// Saves the program, how far it got, and the interpreter's globals.
+    pub fn save(&self, interpreter: &Interpreter, mut writer: impl Write) -> io::Result<()> {
+        writeln!(writer, "{}", HEADER)?;
+        writeln!(writer, "next {}", self.next)?;
+        writeln!(writer, "source {}\n{}", self.source.len(), self.source)?;
+        interpreter.save_state(writer)
+    }
+
+    /// Restores an execution saved by [`Execution::save`], replacing the interpreter's globals.
+    pub fn load(interpreter: &mut Interpreter, mut reader: impl Read) -> io::Result<Self> {
+        let mut checkpoint = String::new();
+        reader.read_to_string(&mut checkpoint)?;
+
+        let invalid = || io::Error::new(io::ErrorKind::InvalidData, "invalid checkpoint");
+        let rest = checkpoint.strip_prefix(HEADER).ok_or_else(invalid)?;
+        let (next, rest) = rest
+            .strip_prefix("\nnext ")
+            .and_then(|rest| rest.split_once('\n'))
+            .ok_or_else(invalid)?;
+        let (len, rest) = rest
+            .strip_prefix("source ")
+            .and_then(|rest| rest.split_once('\n'))
+            .ok_or_else(invalid)?;
+        let next: usize = next.parse().map_err(|_| invalid())?;
+        let len: usize = len.parse().map_err(|_| invalid())?;
+        let source = rest
+            .get(..len)
+            .filter(|_| rest[len..].starts_with('\n'))
+            .ok_or_else(invalid)?;
+        let state = &rest[len + 1..];
+
+        // the source was checked when the execution started, against globals that may since have changed
+        let mut scanner = Scanner::with_dialect(source.into(), interpreter.dialect());
+        let statements = Parser::new(scanner.scan_tokens())
+            .parse()
+            .into_result()
+            .map_err(|_| invalid())?;
+        if next > statements.len() {
+            return Err(invalid());
+        }
+        interpreter.load_state(state.as_bytes())?;
+
+        Ok(Execution {
+            source: source.into(),
+            statements,
+            next,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{
+        interpreter::IntrResult,
+        messages::Message,
+        token::{Token, TokenType},
+    };
+
+    use super::*;
+
+    const PROGRAM: &str = "var a = 1;\nprint a;\na = a + 1;\nprint a;\na = a * 10;\nprint a;";
+
+    #[test]
+    fn test_run_in_slices() {
+        let mut interpreter = Interpreter::default();
+        let mut execution = Execution::new(&interpreter, PROGRAM).unwrap();
+
+        // `print a;` is one step, `a = a + 1;` four
+        assert_eq!(execution.run(&mut interpreter, 2), Ok(Progress::Paused));
+        assert_eq!(interpreter.take_stdout(), "1\n");
+        assert_eq!(execution.run(&mut interpreter, 5), Ok(Progress::Paused));
+        assert_eq!(interpreter.take_stdout(), "2\n");
+        assert_eq!(execution.run(&mut interpreter, 100), Ok(Progress::Finished));
+        assert_eq!(interpreter.take_stdout(), "20\n");
+        assert_eq!(execution.run(&mut interpreter, 100), Ok(Progress::Finished));
+    }
+
+    #[test]
+    fn test_resume_elsewhere() {
+        let mut interpreter = Interpreter::default();
+        let mut execution = Execution::new(&interpreter, PROGRAM).unwrap();
+        execution.run(&mut interpreter, 7).unwrap();
+        assert_eq!(interpreter.take_stdout(), "1\n2\n");
+
+        let mut saved = vec![];
+        execution.save(&interpreter, &mut saved).unwrap();
+
+        let mut elsewhere = Interpreter::default();
+        let mut resumed = Execution::load(&mut elsewhere, saved.as_slice()).unwrap();
+        assert_eq!(resumed.run(&mut elsewhere, 100), Ok(Progress::Finished));
+        assert_eq!(elsewhere.take_stdout(), "20\n");
+
+        let truncated = &saved[..saved.len() / 2];
+        assert!(Execution::load(&mut Interpreter::default(), truncated).is_err());
+    }
+
+    #[test]
+    fn test_errors() {
+        let interpreter = Interpreter::default();
+        let Err(diagnostics) = Execution::new(&interpreter, "var = 1; print;") else {
+            panic!("expected parse errors");
+        };
+        assert_eq!(diagnostics.len(), 2);
+
+        let mut interpreter = Interpreter::default();
+        let mut execution = Execution::new(&interpreter, "print -nil;\nvar b = 2;").unwrap();
+        let error = execution.run(&mut interpreter, 100).unwrap_err();
+        assert_eq!(error.message, Message::OperandMustBeNumber);
+        assert_eq!(execution.run(&mut interpreter, 100), Ok(Progress::Finished));
+        let token = Token::new(TokenType::Identifier, "b", None, 1);
+        assert_eq!(interpreter.environment().get(&token), Ok(&IntrResult::Number(2.0)));
+    }
+}
@@ -0,0 +1,173 @@
+//! Top-level handling of interpreter panics: an apology, what was running, and a reproducer on disk
+//! instead of a raw backtrace. Nothing is sent anywhere.
+
+use std::{
+    cell::RefCell,
+    collections::hash_map::RandomState,
+    fs,
+    hash::{BuildHasher, Hasher},
+    io,
+    panic::{self, UnwindSafe},
+    path::PathBuf,
+};
+
+const ISSUES: &str = "https://github.com/borolgs/lox/issues";
+
+/// What the interpreter was doing when it panicked.
+#[derive(Debug, Default, Clone)]
+pub struct Context {
+    /// Script path, `None` for the REPL.
+    pub file: Option<String>,
+    /// Line the source starts at: the line of the REPL session it came from, 1 for a file.
+    pub line: usize,
+    pub source: String,
+    /// The top-level statement being executed: its line within `source` and its text.
+    pub statement: Option<(usize, String)>,
+}
+
+thread_local! {
+    static CONTEXT: RefCell<Option<Context>> = const { RefCell::new(None) };
+    static PANIC: RefCell<Option<String>> = const { RefCell::new(None) };
+}
+
+/// Records the source about to be run, reported if it crashes the interpreter.
+pub fn set_context(context: Context) {
+    CONTEXT.with(|current| *current.borrow_mut() = Some(context));
+}
+
+/// Records the top-level statement about to be executed, at `line` of the current context's source.
+pub fn set_statement(line: usize, text: &str) {
+    CONTEXT.with(|current| {
+        if let Some(context) = current.borrow_mut().as_mut() {
+            context.statement = Some((line, text.into()));
+        }
+    });
+}
+
+/// Runs `f`, turning a panic into a crash report on stderr. Returns `None` if `f` panicked.
+pub fn guard<R>(args: &[String], f: impl FnOnce() -> R + UnwindSafe) -> Option<R> {
+    panic::set_hook(Box::new(|info| {
+        PANIC.with(|panic| *panic.borrow_mut() = Some(info.to_string()));
+    }));
+    let res = panic::catch_unwind(f);
+    let _ = panic::take_hook();
+
+    if res.is_err() {
+        let context = CONTEXT.with(|context| context.borrow().clone());
+        let panic = PANIC.with(|panic| panic.borrow_mut().take()).unwrap_or_default();
+        // without a context the crash happened before any source was loaded, there's nothing to reproduce
+        let bundle = context.as_ref().and_then(|context| write_bundle(args, context).ok());
+        eprintln!("{}", report(context.as_ref(), &panic, bundle.as_ref()));
+    }
+
+    res.ok()
+}
+
+fn report(context: Option<&Context>, panic: &str, bundle: Option<&PathBuf>) -> String {
+    // line within the whole file or REPL session
+    let line = context.map(|context| match &context.statement {
+        Some((line, _)) => context.line + line - 1,
+        None => context.line,
+    });
+    let location = match (context, line) {
+        (Some(Context { file: Some(file), .. }), Some(line)) => format!("{}, line {}", file, line),
+        (Some(_), Some(line)) => format!("REPL input line {}", line),
+        _ => "startup, no source loaded yet".into(),
+    };
+    let snippet = match context {
+        // continuation lines line up under the first one
+        Some(Context {
+            statement: Some((_, text)),
+            ..
+        }) => text.lines().collect::<Vec<_>>().join("\n               "),
+        Some(context) => context.source.lines().next().unwrap_or_default().into(),
+        None => String::new(),
+    };
+
+    let mut report = format!(
+        "Sorry, the Lox interpreter crashed. This is a bug in the interpreter, not in your program.\n\n\
+         while running: {}\n\
+         source:        {}\n\
+         panic:         {}\n\n",
+        location, snippet, panic
+    );
+    match bundle {
+        Some(bundle) => report.push_str(&format!(
+            "A reproducer was written to {}. Please attach it to a new issue at {}",
+            bundle.display(),
+            ISSUES
+        )),
+        None => report.push_str(&format!("Please open an issue at {} with the source above.", ISSUES)),
+    }
+    report
+}
+
+/// Writes the crashing source and the command line to a fresh temp directory.
+fn write_bundle(args: &[String], context: &Context) -> io::Result<PathBuf> {
+    let dir = create_unique_dir()?;
+    fs::write(dir.join("repro.lox"), &context.source)?;
+    fs::write(dir.join("args.txt"), args.join("\n"))?;
+    Ok(dir)
+}
+
+/// Creates a new directory with an unguessable name under the temp dir, never reusing an existing one.
+fn create_unique_dir() -> io::Result<PathBuf> {
+    loop {
+        let suffix = RandomState::new().build_hasher().finish();
+        let dir = std::env::temp_dir().join(format!("lox-crash-{}-{:016x}", std::process::id(), suffix));
+        match fs::create_dir(&dir) {
+            Err(err) if err.kind() == io::ErrorKind::AlreadyExists => continue,
+            res => return res.map(|_| dir),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_report() {
+        let context = Context {
+            file: Some("main.lox".into()),
+            line: 1,
+            source: "1 +\n2".into(),
+            statement: None,
+        };
+        let text = report(Some(&context), "boom", Some(&PathBuf::from("/tmp/lox-crash-1")));
+        assert!(text.contains("while running: main.lox, line 1\n"));
+        assert!(text.contains("source:        1 +\n"));
+        assert!(text.contains("panic:         boom\n"));
+        assert!(text.contains("written to /tmp/lox-crash-1"));
+
+        // the statement being executed, not the start of the file
+        let context = Context {
+            statement: Some((3, "print\n  a;".into())),
+            ..context
+        };
+        let text = report(Some(&context), "boom", None);
+        assert!(text.contains("while running: main.lox, line 3\n"));
+        assert!(text.contains("source:        print\n                 a;\n"));
+
+        let context = Context {
+            file: None,
+            line: 3,
+            source: "nil;\n-nil;".into(),
+            statement: Some((2, "-nil;".into())),
+        };
+        assert!(report(Some(&context), "boom", None).contains("REPL input line 4"));
+
+        let text = report(None, "boom", None);
+        assert!(text.contains("while running: startup, no source loaded yet\n"));
+        assert!(text.contains("Please open an issue"));
+    }
+
+    #[test]
+    fn test_unique_dir() {
+        let first = create_unique_dir().unwrap();
+        let second = create_unique_dir().unwrap();
+        assert_ne!(first, second);
+        fs::remove_dir(first).unwrap();
+        fs::remove_dir(second).unwrap();
+    }
+}
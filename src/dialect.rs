@@ -0,0 +1,9 @@
+/// Opt-in language variations on top of the book's Lox.
+#[derive(Debug, Default, Clone, Copy, PartialEq)]
+pub struct Dialect {
+    /// A newline ends a statement when the line could end one and the next token can't continue it.
+    pub newline_terminators: bool,
+    /// Assigning to a variable that was never declared is rejected before the program runs.
+    /// Also enabled per file by a leading `"use strict";`.
+    pub strict: bool,
+}
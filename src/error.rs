@@ -1,7 +1,16 @@
 // TODO: https://craftinginterpreters.com/scanning.html#error-handling
 
-use crate::{interpreter::IntrError, parser::ParserError};
+use std::fmt::Display;
 
+use crate::{
+    interpreter::IntrError,
+    messages::{Lang, Message},
+    parser::ParserError,
+    scanner::ScanError,
+    strict::StrictError,
+};
+
+#[derive(Debug)]
 pub enum LoxError {
     ParseError(ParserError),
     RuntimeError(IntrError),
@@ -18,3 +27,78 @@ impl From<IntrError> for LoxError {
         LoxError::RuntimeError(error)
     }
 }
+
+/// A reportable problem, decoupled from the phase that produced it.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Diagnostic {
+    pub message: Message,
+    pub line: Option<usize>,
+}
+
+impl From<LoxError> for Diagnostic {
+    fn from(error: LoxError) -> Self {
+        match error {
+            LoxError::ParseError(ParserError::ParseError(token, message)) => Diagnostic {
+                message,
+                line: Some(token.line),
+            },
+            LoxError::RuntimeError(IntrError::Runtime(token, message)) => Diagnostic {
+                message,
+                line: Some(token.line),
+            },
+            LoxError::RuntimeError(IntrError::StepLimit(max_steps)) => Diagnostic {
+                message: Message::StepLimit { max_steps },
+                line: None,
+            },
+            LoxError::RuntimeError(IntrError::Output(error)) => Diagnostic {
+                message: Message::OutputFailed { error },
+                line: None,
+            },
+        }
+    }
+}
+
+impl From<ParserError> for Diagnostic {
+    fn from(error: ParserError) -> Self {
+        LoxError::from(error).into()
+    }
+}
+
+impl From<IntrError> for Diagnostic {
+    fn from(error: IntrError) -> Self {
+        LoxError::from(error).into()
+    }
+}
+
+impl From<ScanError> for Diagnostic {
+    fn from(ScanError(line, message): ScanError) -> Self {
+        Diagnostic {
+            message,
+            line: Some(line),
+        }
+    }
+}
+
+impl From<StrictError> for Diagnostic {
+    fn from(StrictError(token, message): StrictError) -> Self {
+        Diagnostic {
+            message,
+            line: Some(token.line),
+        }
+    }
+}
+
+impl Diagnostic {
+    pub fn render(&self, lang: Lang) -> String {
+        match self.line {
+            Some(line) => format!("{}\n{}", self.message.render(lang), Message::Line { line }.render(lang)),
+            None => self.message.render(lang),
+        }
+    }
+}
+
+impl Display for Diagnostic {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", self.render(Lang::En))
+    }
+}
@@ -0,0 +1,166 @@
+//! C ABI for embedding the interpreter from non-Rust hosts.
+//!
+//! Build the shared library with `cargo rustc --lib --release --features ffi --crate-type cdylib`.
+//! Strings returned by `lox_eval` and `lox_last_error` are owned by the handle and stay valid
+//! until the next `lox_eval` call or `lox_free`. Each handle is one interpreter session: globals
+//! declared by one `lox_eval` are visible to the next.
+
+use std::{
+    ffi::{c_char, CStr, CString},
+    ptr,
+};
+
+use crate::{interpreter::Interpreter, run_with};
+
+pub struct Lox {
+    interpreter: Interpreter,
+    last_result: Option<CString>,
+    last_error: Option<CString>,
+}
+
+/// Creates a new interpreter handle. Release it with `lox_free`.
+#[no_mangle]
+pub extern "C" fn lox_new() -> *mut Lox {
+    Box::into_raw(Box::new(Lox {
+        interpreter: Interpreter::default(),
+        last_result: None,
+        last_error: None,
+    }))
+}
+
+/// Runs `source` and returns the stringified value of a lone expression, or what a program printed,
+/// or null on error (see `lox_last_error`).
+///
+/// # Safety
+///
+/// `lox` must come from `lox_new` and `source` must be a valid NUL-terminated string.
+#[no_mangle]
+pub unsafe extern "C" fn lox_eval(lox: *mut Lox, source: *const c_char) -> *const c_char {
+    let Some(lox) = lox.as_mut() else {
+        return ptr::null();
+    };
+    lox.last_result = None;
+    lox.last_error = None;
+
+    if source.is_null() {
+        lox.last_error = CString::new("source is null").ok();
+        return ptr::null();
+    }
+    let source = match CStr::from_ptr(source).to_str() {
+        Ok(source) => source,
+        Err(_) => {
+            lox.last_error = CString::new("source is not valid UTF-8").ok();
+            return ptr::null();
+        }
+    };
+
+    let outcome = run_with(&mut lox.interpreter, source);
+    if !outcome.diagnostics.is_empty() {
+        let message = outcome
+            .diagnostics
+            .iter()
+            .map(|d| d.to_string())
+            .collect::<Vec<_>>()
+            .join("\n");
+        lox.last_error = Some(to_c_string(message));
+        return ptr::null();
+    }
+
+    let result = match outcome.value {
+        Some(value) => lox.interpreter.stringify(&value),
+        None => outcome.stdout,
+    };
+    lox.last_result.insert(to_c_string(result)).as_ptr()
+}
+
+/// Returns the message of the last failed `lox_eval`, or null if it succeeded.
+///
+/// # Safety
+///
+/// `lox` must come from `lox_new`.
+#[no_mangle]
+pub unsafe extern "C" fn lox_last_error(lox: *const Lox) -> *const c_char {
+    match lox.as_ref().and_then(|lox| lox.last_error.as_ref()) {
+        Some(err) => err.as_ptr(),
+        None => ptr::null(),
+    }
+}
+
+/// Releases a handle created by `lox_new`. Passing null is a no-op.
+///
+/// # Safety
+///
+/// `lox` must come from `lox_new` and must not be used afterwards.
+#[no_mangle]
+pub unsafe extern "C" fn lox_free(lox: *mut Lox) {
+    if !lox.is_null() {
+        drop(Box::from_raw(lox));
+    }
+}
+
+fn to_c_string(value: String) -> CString {
+    CString::new(value.replace('\0', "\\0")).expect("interior NUL bytes are escaped")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn eval(lox: *mut Lox, source: &str) -> Result<String, String> {
+        let source = CString::new(source).unwrap();
+        unsafe {
+            let res = lox_eval(lox, source.as_ptr());
+            if res.is_null() {
+                Err(CStr::from_ptr(lox_last_error(lox)).to_str().unwrap().to_string())
+            } else {
+                Ok(CStr::from_ptr(res).to_str().unwrap().to_string())
+            }
+        }
+    }
+
+    #[test]
+    fn test_eval() {
+        let lox = lox_new();
+        assert_eq!(eval(lox, "1 + 2 * 3"), Ok("7".into()));
+        assert_eq!(eval(lox, "\"a\" + \"b\""), Ok("ab".into()));
+        unsafe { lox_free(lox) };
+    }
+
+    #[test]
+    fn test_last_error() {
+        let lox = lox_new();
+        assert_eq!(
+            eval(lox, "5 + true"),
+            Err("Operands must be two numbers or two strings.\n[line 1]".into())
+        );
+        assert_eq!(eval(lox, "nil"), Ok("nil".into()));
+        assert!(unsafe { lox_last_error(lox) }.is_null());
+        unsafe { lox_free(lox) };
+    }
+
+    #[test]
+    fn test_program() {
+        let lox = lox_new();
+        assert_eq!(eval(lox, "print 1; print \"two\";"), Ok("1\ntwo\n".into()));
+        assert_eq!(eval(lox, "var a = 1;"), Ok("".into()));
+        assert!(unsafe { lox_last_error(lox) }.is_null());
+        assert_eq!(eval(lox, "print;"), Err("Expect expression.\n[line 1]".into()));
+        unsafe { lox_free(lox) };
+    }
+
+    #[test]
+    fn test_session() {
+        let lox = lox_new();
+        assert_eq!(eval(lox, "var a = 1;"), Ok("".into()));
+        assert_eq!(eval(lox, "a = a + 1;"), Ok("".into()));
+        assert_eq!(eval(lox, "a * 10"), Ok("20".into()));
+
+        // handles don't share globals
+        let other = lox_new();
+        assert_eq!(eval(other, "a"), Err("Undefined variable 'a'.\n[line 1]".into()));
+        unsafe {
+            lox_free(lox);
+            lox_free(other);
+        }
+    }
+}
@@ -1,126 +1,354 @@
+use std::{collections::HashMap, fmt::Display, io::Write, rc::Rc};
+
 use crate::{
-    ast::Expr,
-    token::{Token, TokenType},
+    ast::{Expr, Stmt},
+    dialect::Dialect,
+    messages::Message,
+    token::{Token, TokenType, KEYWORDS},
 };
 
-pub struct Interpreter;
+/// Called with a top-level statement's line and text before it runs.
+type StatementHook = Box<dyn FnMut(usize, &str)>;
 
-#[derive(Debug, PartialEq)]
+#[derive(Default)]
+pub struct Interpreter {
+    /// Evaluation steps since the last [`Interpreter::reset_steps`]; one per expression node.
+    steps: usize,
+    pub allocations: usize,
+    /// Statements run by [`Interpreter::execute`]; a lone expression evaluated on its own isn't one.
+    pub statements_executed: usize,
+    max_steps: Option<usize>,
+    precision: Option<usize>,
+    dialect: Dialect,
+    environment: Environment,
+    /// Where `print` writes, see [`InterpreterBuilder::output`].
+    output: Option<Box<dyn Write>>,
+    on_statement: Option<StatementHook>,
+    /// Text written by `print` since the last [`Interpreter::take_stdout`], when there is no `output`.
+    stdout: String,
+}
+
+/// Variable bindings, keyed by name.
+#[derive(Debug, Default)]
+pub struct Environment {
+    values: HashMap<String, IntrResult>,
+}
+
+impl Environment {
+    /// Binds `name`, replacing any previous binding: redeclaring a global is allowed.
+    pub fn define(&mut self, name: &str, value: IntrResult) {
+        self.values.insert(name.to_string(), value);
+    }
+
+    /// Every binding, in no particular order.
+    pub fn iter(&self) -> impl Iterator<Item = (&str, &IntrResult)> {
+        self.values.iter().map(|(name, value)| (name.as_str(), value))
+    }
+
+    pub fn contains(&self, name: &str) -> bool {
+        self.values.contains_key(name)
+    }
+
+    pub fn get(&self, name: &Token) -> Result<&IntrResult, IntrError> {
+        self.values.get(name.lexeme()).ok_or_else(|| undefined(name))
+    }
+
+    /// Rebinds an existing variable; unlike `define` it's an error if `name` was never declared.
+    pub fn assign(&mut self, name: &Token, value: IntrResult) -> Result<(), IntrError> {
+        match self.values.get_mut(name.lexeme()) {
+            Some(slot) => {
+                *slot = value;
+                Ok(())
+            }
+            None => Err(undefined(name)),
+        }
+    }
+}
+
+fn undefined(name: &Token) -> IntrError {
+    IntrError::Runtime(
+        name.clone(),
+        Message::UndefinedVariable {
+            name: name.lexeme().to_string(),
+        },
+    )
+}
+
+#[derive(Default)]
+pub struct InterpreterBuilder {
+    max_steps: Option<usize>,
+    precision: Option<usize>,
+    dialect: Dialect,
+    output: Option<Box<dyn Write>>,
+    on_statement: Option<StatementHook>,
+}
+
+impl InterpreterBuilder {
+    /// Aborts evaluation with [`IntrError::StepLimit`] once more than `max_steps` expressions were evaluated.
+    pub fn max_steps(mut self, max_steps: usize) -> Self {
+        self.max_steps = Some(max_steps);
+        self
+    }
+
+    /// `print` shows numbers with at most `digits` decimal places, so with 10 `0.1 + 0.2` prints as `0.3`.
+    /// By default numbers print with full precision.
+    pub fn precision(mut self, digits: usize) -> Self {
+        self.precision = Some(digits);
+        self
+    }
+
+    pub fn dialect(mut self, dialect: Dialect) -> Self {
+        self.dialect = dialect;
+        self
+    }
+
+    /// `print` writes to `output` as each statement runs. By default the text is kept in memory instead,
+    /// for [`Interpreter::take_stdout`].
+    pub fn output(mut self, output: impl Write + 'static) -> Self {
+        self.output = Some(Box::new(output));
+        self
+    }
+
+    /// Calls `hook` with the line and text of each top-level statement before [`crate::run_with`] or
+    /// [`crate::run_streaming`] runs it, e.g. to tell what was running when something went wrong.
+    pub fn on_statement(mut self, hook: impl FnMut(usize, &str) + 'static) -> Self {
+        self.on_statement = Some(Box::new(hook));
+        self
+    }
+
+    pub fn build(self) -> Interpreter {
+        Interpreter {
+            max_steps: self.max_steps,
+            precision: self.precision,
+            dialect: self.dialect,
+            output: self.output,
+            on_statement: self.on_statement,
+            ..Default::default()
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
 pub enum IntrResult {
     Number(f64),
-    String(String),
+    /// Shared, since strings are immutable: copying the value, e.g. reading a variable, doesn't copy the text.
+    String(Rc<str>),
     Bool(bool),
     None,
 }
 
+impl IntrResult {
+    /// `nil` and `false` are falsey, every other value is truthy.
+    pub fn is_truthy(&self) -> bool {
+        !matches!(self, IntrResult::None | IntrResult::Bool(false))
+    }
+}
+
+impl Display for IntrResult {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            IntrResult::Number(number) => write!(f, "{}", number),
+            IntrResult::String(value) => write!(f, "{}", value),
+            IntrResult::Bool(value) => write!(f, "{}", value),
+            IntrResult::None => write!(f, "nil"),
+        }
+    }
+}
+
 #[derive(Debug, PartialEq)]
 pub enum IntrError {
-    Runtime(Token, String),
-    Unsupported(Token),
-    NotImplemented(Token),
+    Runtime(Token, Message),
+    StepLimit(usize),
+    /// Writing `print` output failed, e.g. the pipe was closed.
+    Output(String),
 }
 
 impl Interpreter {
+    pub fn builder() -> InterpreterBuilder {
+        InterpreterBuilder::default()
+    }
+
+    /// Deterministic count of evaluation steps, independent of timing and platform.
+    pub fn steps_executed(&self) -> usize {
+        self.steps
+    }
+
+    /// Starts a new step count, also restarting the `max_steps` budget.
+    pub fn reset_steps(&mut self) {
+        self.steps = 0;
+    }
+
+    pub fn dialect(&self) -> Dialect {
+        self.dialect
+    }
+
+    pub(crate) fn enter_statement(&mut self, line: usize, text: &str) {
+        if let Some(hook) = &mut self.on_statement {
+            hook(line, text);
+        }
+    }
+
+    /// Pushes what `print` wrote to the output, if there is one, past any buffering.
+    pub fn flush_output(&mut self) -> Result<(), IntrError> {
+        match &mut self.output {
+            Some(output) => output.flush().map_err(|err| IntrError::Output(err.to_string())),
+            None => Ok(()),
+        }
+    }
+
+    /// Hands over everything printed so far, leaving the buffer empty. Always empty with an output set.
+    pub fn take_stdout(&mut self) -> String {
+        std::mem::take(&mut self.stdout)
+    }
+
+    /// How `print` shows `value`, honoring [`InterpreterBuilder::precision`].
+    pub fn stringify(&self, value: &IntrResult) -> String {
+        match (value, self.precision) {
+            (IntrResult::Number(number), Some(digits)) if number.is_finite() => {
+                let fixed = format!("{:.*}", digits, number);
+                match fixed.contains('.') {
+                    true => fixed.trim_end_matches('0').trim_end_matches('.').to_string(),
+                    false => fixed,
+                }
+            }
+            _ => value.to_string(),
+        }
+    }
+
+    /// How a REPL shows a value: as `print` would, but with strings quoted, so `"1"` and `1` look different.
+    pub fn repr(&self, value: &IntrResult) -> String {
+        match value {
+            IntrResult::String(string) => format!("{:?}", string),
+            _ => self.stringify(value),
+        }
+    }
+
+    pub fn environment(&self) -> &Environment {
+        &self.environment
+    }
+
+    /// Declared globals and keywords that start with `prefix`, sorted, for completing a name in a REPL.
+    pub fn completions(&self, prefix: &str) -> Vec<&str> {
+        let globals = self.environment.iter().map(|(name, _)| name);
+        let mut names = globals
+            .chain(KEYWORDS.iter().copied())
+            .filter(|name| name.starts_with(prefix))
+            .collect::<Vec<_>>();
+        names.sort_unstable();
+        names.dedup();
+        names
+    }
+
+    pub(crate) fn environment_mut(&mut self) -> &mut Environment {
+        &mut self.environment
+    }
+
+    pub fn execute(&mut self, stmt: &Stmt) -> Result<(), IntrError> {
+        self.statements_executed += 1;
+        match stmt {
+            Stmt::Expression(expr) => {
+                self.evaluate(expr)?;
+            }
+            Stmt::Print(expr) => {
+                let value = self.evaluate(expr)?;
+                let text = format!("{}\n", self.stringify(&value));
+                match &mut self.output {
+                    Some(output) => output
+                        .write_all(text.as_bytes())
+                        .map_err(|err| IntrError::Output(err.to_string()))?,
+                    None => self.stdout.push_str(&text),
+                }
+            }
+            Stmt::Var(name, initializer) => {
+                let value = match initializer {
+                    Some(initializer) => self.evaluate(initializer)?,
+                    None => IntrResult::None,
+                };
+                self.environment.define(name.lexeme(), value);
+            }
+        }
+
+        Ok(())
+    }
+
+    fn binary(&mut self, operator: &Token, left: IntrResult, right: IntrResult) -> Result<IntrResult, IntrError> {
+        use IntrResult::{Bool, Number};
+
+        match (operator.token_type, left, right) {
+            (TokenType::Minus, Number(left), Number(right)) => Ok(Number(left - right)),
+            (TokenType::Plus, Number(left), Number(right)) => Ok(Number(left + right)),
+            (TokenType::Percent, Number(left), Number(right)) => Ok(Number(left % right)),
+            (TokenType::Slash, Number(left), Number(right)) => Ok(Number(left / right)),
+            (TokenType::Star, Number(left), Number(right)) => Ok(Number(left * right)),
+            (TokenType::Greater, Number(left), Number(right)) => Ok(Bool(left > right)),
+            (TokenType::GreaterEqual, Number(left), Number(right)) => Ok(Bool(left >= right)),
+            (TokenType::Less, Number(left), Number(right)) => Ok(Bool(left < right)),
+            (TokenType::LessEqual, Number(left), Number(right)) => Ok(Bool(left <= right)),
+            (TokenType::Plus, IntrResult::String(left), IntrResult::String(right)) => {
+                self.allocations += 1;
+                Ok(IntrResult::String([left, right].concat().into()))
+            }
+            (TokenType::EqualEqual, left, right) => Ok(IntrResult::Bool(left == right)),
+            (TokenType::BangEqual, left, right) => Ok(IntrResult::Bool(left != right)),
+            (TokenType::Plus, _, _) => Err(IntrError::Runtime(
+                operator.clone(),
+                Message::OperandsMustBeNumbersOrStrings,
+            )),
+            _ => Err(IntrError::Runtime(operator.clone(), Message::OperandsMustBeNumbers)),
+        }
+    }
+
     pub fn evaluate(&mut self, expr: &Expr) -> Result<IntrResult, IntrError> {
+        self.steps += 1;
+        if let Some(max_steps) = self.max_steps {
+            if self.steps > max_steps {
+                return Err(IntrError::StepLimit(max_steps));
+            }
+        }
+
         match expr {
             Expr::Binary(left, operator, right) => {
                 let left = self.evaluate(left)?;
                 let right = self.evaluate(right)?;
 
-                match (operator.token_type, left, right) {
-                    (
-                        TokenType::Minus, // -
-                        IntrResult::Number(left),
-                        IntrResult::Number(right),
-                    ) => Ok(IntrResult::Number(left - right)),
-                    (
-                        TokenType::Slash, // /
-                        IntrResult::Number(left),
-                        IntrResult::Number(right),
-                    ) => Ok(IntrResult::Number(left / right)),
-                    (
-                        TokenType::Star, // *
-                        IntrResult::Number(left),
-                        IntrResult::Number(right),
-                    ) => Ok(IntrResult::Number(left * right)),
-                    (
-                        TokenType::Plus, // +
-                        IntrResult::Number(left),
-                        IntrResult::Number(right),
-                    ) => Ok(IntrResult::Number(left + right)),
-                    (
-                        TokenType::Plus, // + string string
-                        IntrResult::String(left),
-                        IntrResult::String(right),
-                    ) => Ok(IntrResult::String(left + right.as_ref())),
-                    (
-                        TokenType::Greater, // >
-                        IntrResult::Number(left),
-                        IntrResult::Number(right),
-                    ) => Ok(IntrResult::Bool(left > right)),
-                    (
-                        TokenType::GreaterEqual, // >=
-                        IntrResult::Number(left),
-                        IntrResult::Number(right),
-                    ) => Ok(IntrResult::Bool(left >= right)),
-                    (
-                        TokenType::Less, // <
-                        IntrResult::Number(left),
-                        IntrResult::Number(right),
-                    ) => Ok(IntrResult::Bool(left < right)),
-                    (
-                        TokenType::LessEqual, // <=
-                        IntrResult::Number(left),
-                        IntrResult::Number(right),
-                    ) => Ok(IntrResult::Bool(left <= right)),
-                    (
-                        TokenType::EqualEqual, // ==
-                        IntrResult::Number(left),
-                        IntrResult::Number(right),
-                    ) => Ok(IntrResult::Bool(left == right)),
-                    (
-                        TokenType::EqualEqual, // == string string
-                        IntrResult::String(left),
-                        IntrResult::String(right),
-                    ) => Ok(IntrResult::Bool(left == right)),
-                    (
-                        TokenType::EqualEqual, // == nil nil
-                        IntrResult::None,
-                        IntrResult::None,
-                    ) => Ok(IntrResult::Bool(true)),
-                    (
-                        TokenType::BangEqual, // !=
-                        IntrResult::Number(left),
-                        IntrResult::Number(right),
-                    ) => Ok(IntrResult::Bool(left == right)),
-                    (
-                        TokenType::BangEqual, // != string string
-                        IntrResult::String(left),
-                        IntrResult::String(right),
-                    ) => Ok(IntrResult::Bool(left == right)),
-                    _ => Err(IntrError::Unsupported(operator.clone())),
+                self.binary(operator, left, right)
+            }
+            Expr::Logical(left, operator, right) => {
+                let left = self.evaluate(left)?;
+
+                // the deciding operand is returned as is, not coerced to a bool
+                let short_circuits = match operator.token_type {
+                    TokenType::Or => left.is_truthy(),
+                    _ => !left.is_truthy(),
+                };
+                if short_circuits {
+                    return Ok(left);
                 }
+                self.evaluate(right)
             }
             Expr::Grouping(expr) => self.evaluate(expr),
             Expr::Unary(operator, expr) => {
                 let right = self.evaluate(expr)?;
 
                 match (operator.token_type, right) {
-                    (TokenType::Bang, IntrResult::Number(number)) => Ok(IntrResult::Bool(number > 0.0)),
-                    (TokenType::Bang, IntrResult::Bool(value)) => Ok(IntrResult::Bool(!value)),
-                    (TokenType::Bang, IntrResult::None) => Ok(IntrResult::Bool(false)),
-                    (TokenType::Bang, _) => Ok(IntrResult::Bool(true)),
+                    (TokenType::Bang, right) => Ok(IntrResult::Bool(!right.is_truthy())),
                     (TokenType::Minus, IntrResult::Number(number)) => Ok(IntrResult::Number(-number)),
-                    _ => Err(IntrError::Unsupported(operator.clone())),
+                    _ => Err(IntrError::Runtime(operator.clone(), Message::OperandMustBeNumber)),
                 }
             }
             Expr::Literal(literal) => match literal {
-                crate::token::Literal::String(value) => Ok(IntrResult::String(value.clone())),
+                crate::token::Literal::String(value) => Ok(IntrResult::String(Rc::clone(value))),
                 crate::token::Literal::Number(number) => Ok(IntrResult::Number(*number)),
                 crate::token::Literal::True => Ok(IntrResult::Bool(true)),
                 crate::token::Literal::False => Ok(IntrResult::Bool(false)),
                 crate::token::Literal::Nil => Ok(IntrResult::None),
             },
+            Expr::Variable(name) => Ok(self.environment.get(name)?.clone()),
+            Expr::Assign(name, value) => {
+                let value = self.evaluate(value)?;
+                self.environment.assign(name, value.clone())?;
+                Ok(value)
+            }
         }
     }
 }
@@ -136,10 +364,68 @@ mod tests {
         let tokens = scanner.scan_tokens();
         let mut parser = Parser::new(tokens);
         let expr = parser.expression().unwrap();
-        let mut interpreter = Interpreter;
+        let mut interpreter = Interpreter::default();
         interpreter.evaluate(&expr)
     }
 
+    fn execute(input: &str) -> Result<Interpreter, IntrError> {
+        let mut scanner = scanner::Scanner::new(input.into());
+        let tokens = scanner.scan_tokens();
+        let statements = Parser::new(tokens).parse().into_result().unwrap();
+        let mut interpreter = Interpreter::default();
+        for stmt in &statements {
+            interpreter.execute(stmt)?;
+        }
+        Ok(interpreter)
+    }
+
+    fn global(interpreter: &Interpreter, name: &str) -> IntrResult {
+        let token = Token::new(TokenType::Identifier, name, None, 1);
+        interpreter.environment().get(&token).unwrap().clone()
+    }
+
+    #[test]
+    fn test_variables() {
+        let interpreter = execute("var a = 1; var b; var c = a + 2; a = c = a * 10; var a = a + 1;").unwrap();
+        assert_eq!(global(&interpreter, "a"), IntrResult::Number(11.0));
+        assert_eq!(global(&interpreter, "b"), IntrResult::None);
+        assert_eq!(global(&interpreter, "c"), IntrResult::Number(10.0));
+    }
+
+    #[test]
+    fn test_completions() {
+        let interpreter = execute("var value = 1; var variance; var total;").unwrap();
+        assert_eq!(interpreter.completions("va"), ["value", "var", "variance"]);
+        assert_eq!(interpreter.completions("t"), ["this", "total", "true"]);
+        assert!(interpreter.completions("x").is_empty());
+        assert_eq!(interpreter.completions("").len(), KEYWORDS.len() + 3);
+    }
+
+    #[test]
+    fn test_compound_assignment() {
+        let interpreter =
+            execute("var a = 10; a += 5; a -= 1; a *= 3; a /= 6; a %= 4; var s = \"a\"; s += \"b\";").unwrap();
+        assert_eq!(global(&interpreter, "a"), IntrResult::Number(3.0));
+        assert_eq!(global(&interpreter, "s"), IntrResult::String("ab".into()));
+
+        let Err(IntrError::Runtime(token, message)) = execute("var a = 1;\na -= \"b\";") else {
+            panic!("expected a runtime error");
+        };
+        assert_eq!((token.lexeme(), token.line), ("-", 2));
+        assert_eq!(message, Message::OperandsMustBeNumbers);
+    }
+
+    #[test]
+    fn test_undefined_variable() {
+        for input in ["a;", "a = 1;", "var b = a;"] {
+            let Err(IntrError::Runtime(token, message)) = execute(input) else {
+                panic!("expected a runtime error for {}", input);
+            };
+            assert_eq!(token.lexeme(), "a");
+            assert_eq!(message.to_string(), "Undefined variable 'a'.");
+        }
+    }
+
     #[test]
     fn test_evaluate_unary_expression() {
         let result = run("-456").unwrap();
@@ -156,10 +442,11 @@ mod tests {
             ("1 + 1 * 3", IntrResult::Number(4.0)),
             ("(1 + 1) * 3", IntrResult::Number(6.0)),
             ("400 - 402", IntrResult::Number(-2.0)),
-            ("\"one\"", IntrResult::String("one".to_string())),
+            ("1e3 * 2E+1 + 5e-1", IntrResult::Number(20000.5)),
+            ("\"one\"", IntrResult::String("one".into())),
             ("\"one\" == \"one\"", IntrResult::Bool(true)),
-            ("\"one\" != \"two\"", IntrResult::Bool(false)),
-            ("\"hello \" + \"world\"", IntrResult::String("hello world".to_string())),
+            ("\"one\" != \"two\"", IntrResult::Bool(true)),
+            ("\"hello \" + \"world\"", IntrResult::String("hello world".into())),
         ];
 
         for (input, expected) in tests.iter() {
@@ -168,9 +455,205 @@ mod tests {
         }
     }
 
+    #[test]
+    fn test_precision() {
+        let number = |n: f64| IntrResult::Number(n);
+        let interpreter = Interpreter::default();
+        assert_eq!(interpreter.stringify(&number(0.1 + 0.2)), "0.30000000000000004");
+
+        let interpreter = Interpreter::builder().precision(10).build();
+        assert_eq!(interpreter.stringify(&number(0.1 + 0.2)), "0.3");
+        assert_eq!(interpreter.stringify(&number(100.0)), "100");
+        assert_eq!(interpreter.stringify(&number(f64::INFINITY)), "inf");
+        assert_eq!(interpreter.stringify(&IntrResult::String("0.10".into())), "0.10");
+
+        let interpreter = Interpreter::builder().precision(2).build();
+        assert_eq!(interpreter.stringify(&number(2.0 / 3.0)), "0.67");
+        let interpreter = Interpreter::builder().precision(0).build();
+        assert_eq!(interpreter.stringify(&number(2.5001)), "3");
+    }
+
+    #[test]
+    fn test_repr() {
+        let interpreter = Interpreter::builder().precision(2).build();
+        let values = [
+            (IntrResult::Number(1.0 / 3.0), "0.33"),
+            (IntrResult::String("say \"hi\"\nnow".into()), "\"say \\\"hi\\\"\\nnow\""),
+            (IntrResult::Bool(true), "true"),
+            (IntrResult::None, "nil"),
+        ];
+        for (value, expected) in values {
+            assert_eq!(interpreter.repr(&value), expected);
+        }
+    }
+
+    #[test]
+    fn test_modulo() {
+        let tests = [
+            ("7 % 3", 1.0),
+            ("-7 % 3", -1.0),
+            ("7.5 % 2", 1.5),
+            ("2 + 9 % 4 * 3", 5.0),
+        ];
+
+        for (input, expected) in tests {
+            assert_eq!(run(input), Ok(IntrResult::Number(expected)), "{}", input);
+        }
+    }
+
+    #[test]
+    fn test_logical_expression() {
+        let tests = [
+            ("true and false", IntrResult::Bool(false)),
+            ("nil or \"default\"", IntrResult::String("default".into())),
+            ("\"first\" or \"second\"", IntrResult::String("first".into())),
+            ("1 and 2", IntrResult::Number(2.0)),
+            ("nil and 2", IntrResult::None),
+            ("false or nil", IntrResult::None),
+            ("1 == 2 or 2 == 2", IntrResult::Bool(true)),
+        ];
+
+        for (input, expected) in tests {
+            assert_eq!(run(input).unwrap(), expected, "{}", input);
+        }
+    }
+
+    #[test]
+    fn test_logical_short_circuit() {
+        // the right operand would be a runtime error if it were evaluated
+        let interpreter = execute("var a = true or -\"a\"; var b = false and undefined;").unwrap();
+        assert_eq!(global(&interpreter, "a"), IntrResult::Bool(true));
+        assert_eq!(global(&interpreter, "b"), IntrResult::Bool(false));
+
+        let interpreter = execute("var a = 1; var b = nil or (a = 2); var c = a and (a = 3);").unwrap();
+        assert_eq!(global(&interpreter, "b"), IntrResult::Number(2.0));
+        assert_eq!(global(&interpreter, "c"), IntrResult::Number(3.0));
+    }
+
+    #[test]
+    fn test_step_limit() {
+        let mut scanner = scanner::Scanner::new("1 + 2 * 3".into());
+        let tokens = scanner.scan_tokens();
+        let expr = Parser::new(tokens).expression().unwrap();
+
+        let mut interpreter = Interpreter::builder().max_steps(5).build();
+        assert_eq!(interpreter.evaluate(&expr), Ok(IntrResult::Number(7.0)));
+
+        let mut interpreter = Interpreter::builder().max_steps(4).build();
+        assert_eq!(interpreter.evaluate(&expr), Err(IntrError::StepLimit(4)));
+    }
+
+    #[test]
+    fn test_steps_executed() {
+        let mut scanner = scanner::Scanner::new("-(1 + 2) * 3".into());
+        let tokens = scanner.scan_tokens();
+        let expr = Parser::new(tokens).expression().unwrap();
+
+        let mut interpreter = Interpreter::builder().max_steps(7).build();
+        interpreter.evaluate(&expr).unwrap();
+        assert_eq!(interpreter.steps_executed(), 7);
+
+        interpreter.reset_steps();
+        assert_eq!(interpreter.steps_executed(), 0);
+        interpreter.evaluate(&expr).unwrap();
+        assert_eq!(interpreter.steps_executed(), 7);
+    }
+
+    #[test]
+    fn test_output() {
+        struct Shared(Rc<std::cell::RefCell<Vec<u8>>>);
+        impl Write for Shared {
+            fn write(&mut self, buf: &[u8]) -> std::io::Result<usize> {
+                self.0.borrow_mut().write(buf)
+            }
+            fn flush(&mut self) -> std::io::Result<()> {
+                Ok(())
+            }
+        }
+
+        let mut scanner = scanner::Scanner::new("print 1; print \"two\";".into());
+        let statements = Parser::new(scanner.scan_tokens()).parse().into_result().unwrap();
+        let written = Rc::new(std::cell::RefCell::new(Vec::new()));
+        let mut interpreter = Interpreter::builder().output(Shared(written.clone())).build();
+        interpreter.execute(&statements[0]).unwrap();
+        // written as soon as the statement runs
+        assert_eq!(written.borrow().as_slice(), b"1\n");
+        interpreter.execute(&statements[1]).unwrap();
+        assert_eq!(written.borrow().as_slice(), b"1\ntwo\n");
+        assert_eq!(interpreter.take_stdout(), "");
+
+        let mut interpreter = Interpreter::builder().output(std::io::Cursor::new([0u8; 0])).build();
+        assert!(matches!(interpreter.execute(&statements[0]), Err(IntrError::Output(_))));
+    }
+
     #[test]
     fn test_evaluate_error() {
         let result = run("5 + true");
         assert!(result.is_err());
     }
+
+    /// Every operator applied to every combination of value types. Arithmetic and comparison need numbers
+    /// (`+` also concatenates strings), equality works on any pair and never coerces, and `!` negates truthiness.
+    #[test]
+    fn test_operator_type_matrix() {
+        let values = ["nil", "true", "false", "0", "2", "\"\"", "\"s\""];
+
+        for right in values {
+            let number = right.parse::<f64>().ok();
+            let falsey = matches!(right, "nil" | "false");
+
+            let expected = match number {
+                Some(number) => Ok(IntrResult::Number(-number)),
+                None => Err("Operand must be a number.".to_string()),
+            };
+            assert_eq!(run(&format!("-{}", right)).map_err(message), expected, "-{}", right);
+            assert_eq!(run(&format!("!{}", right)), Ok(IntrResult::Bool(falsey)), "!{}", right);
+        }
+
+        let operators = ["+", "-", "*", "/", "%", ">", ">=", "<", "<=", "==", "!="];
+        for left in values {
+            for right in values {
+                for operator in operators {
+                    let source = format!("{} {} {}", left, operator, right);
+                    let numbers = left.parse::<f64>().ok().zip(right.parse::<f64>().ok());
+                    let strings = left.starts_with('"') && right.starts_with('"');
+
+                    let expected = match (operator, numbers) {
+                        ("==", _) => Ok(IntrResult::Bool(left == right)),
+                        ("!=", _) => Ok(IntrResult::Bool(left != right)),
+                        ("+", Some((a, b))) => Ok(IntrResult::Number(a + b)),
+                        ("+", None) if strings => Ok(IntrResult::String(
+                            format!("{}{}", left.trim_matches('"'), right.trim_matches('"')).into(),
+                        )),
+                        ("+", None) => Err("Operands must be two numbers or two strings.".to_string()),
+                        ("-", Some((a, b))) => Ok(IntrResult::Number(a - b)),
+                        ("*", Some((a, b))) => Ok(IntrResult::Number(a * b)),
+                        ("/", Some((a, b))) => Ok(IntrResult::Number(a / b)),
+                        ("%", Some((a, b))) => Ok(IntrResult::Number(a % b)),
+                        (">", Some((a, b))) => Ok(IntrResult::Bool(a > b)),
+                        (">=", Some((a, b))) => Ok(IntrResult::Bool(a >= b)),
+                        ("<", Some((a, b))) => Ok(IntrResult::Bool(a < b)),
+                        ("<=", Some((a, b))) => Ok(IntrResult::Bool(a <= b)),
+                        _ => Err("Operands must be numbers.".to_string()),
+                    };
+
+                    let result = run(&source).map_err(message);
+                    match (&result, &expected) {
+                        // 0 / 0, 2 % 0
+                        (Ok(IntrResult::Number(a)), Ok(IntrResult::Number(b))) if a.is_nan() => {
+                            assert!(b.is_nan(), "{}", source)
+                        }
+                        _ => assert_eq!(result, expected, "{}", source),
+                    }
+                }
+            }
+        }
+    }
+
+    fn message(error: IntrError) -> String {
+        match error {
+            IntrError::Runtime(_, message) => message.to_string(),
+            error => panic!("unexpected error {:?}", error),
+        }
+    }
 }
@@ -0,0 +1,488 @@
+pub mod ast;
+pub mod builder;
+pub mod checkpoint;
+pub mod dialect;
+pub mod error;
+#[cfg(feature = "ffi")]
+pub mod ffi;
+pub mod interpreter;
+pub mod messages;
+pub mod notebook;
+pub mod parser;
+pub mod project;
+pub mod scanner;
+pub mod state;
+pub mod stats;
+pub mod strict;
+pub mod template;
+pub mod token;
+
+use ast::{Expr, Stmt};
+use error::Diagnostic;
+use interpreter::{Interpreter, IntrError, IntrResult};
+use parser::{Parser, ParserError};
+use stats::ExecStats;
+use std::{ops::Range, time::Instant};
+use token::TokenType;
+
+/// Everything a host needs from a single run, instead of scraping printed text.
+#[derive(Debug, Default)]
+pub struct RunOutcome {
+    /// Value of the input if it was a lone expression, `None` for programs and failed runs.
+    pub value: Option<IntrResult>,
+    /// Text the program wrote to standard output.
+    pub stdout: String,
+    pub diagnostics: Vec<Diagnostic>,
+    /// Which phase the diagnostics came from, `None` if the run succeeded.
+    pub failure: Option<Failure>,
+    pub stats: ExecStats,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Failure {
+    /// The input didn't scan, parse or pass the strict checks, so nothing ran.
+    Compile,
+    Runtime,
+}
+
+pub fn run(input: &str) -> RunOutcome {
+    run_with(&mut Interpreter::default(), input)
+}
+
+/// Like [`run`], but evaluates with a preconfigured interpreter, e.g. one from [`Interpreter::builder`].
+pub fn run_with(interpreter: &mut Interpreter, input: &str) -> RunOutcome {
+    let mut outcome = RunOutcome::default();
+
+    let start = Instant::now();
+    let mut scanner = scanner::Scanner::with_dialect(input.into(), interpreter.dialect());
+    scanner.scan_tokens();
+    outcome.stats.scan_time = start.elapsed();
+    if !scanner.errors().is_empty() {
+        outcome.diagnostics = scanner.errors().iter().cloned().map(Diagnostic::from).collect();
+        outcome.failure = Some(Failure::Compile);
+        return outcome;
+    }
+    let tokens = scanner.tokens();
+
+    let start = Instant::now();
+    let mut parser = Parser::new(tokens);
+    let parsed = parse(&mut parser);
+    outcome.stats.parse_time = start.elapsed();
+
+    let start = Instant::now();
+    interpreter.reset_steps();
+    interpreter.allocations = 0;
+    interpreter.statements_executed = 0;
+    let res: Result<Option<IntrResult>, (Failure, Vec<Diagnostic>)> = parsed
+        .map_err(|errors| errors.into_iter().map(Diagnostic::from).collect())
+        .and_then(|parsed| {
+            check_strict(interpreter, &parsed)
+                .map(|_| parsed)
+                .map_err(|err| vec![err])
+        })
+        .map_err(|errors| (Failure::Compile, errors))
+        .and_then(|parsed| execute(interpreter, input, &parsed).map_err(|err| (Failure::Runtime, vec![err.into()])));
+    outcome.stats.eval_time = start.elapsed();
+    outcome.stdout = interpreter.take_stdout();
+    outcome.stats.statements_executed = interpreter.statements_executed;
+    outcome.stats.expressions_evaluated = interpreter.steps_executed();
+    outcome.stats.allocations = interpreter.allocations;
+
+    match res {
+        Ok(value) => outcome.value = value,
+        Err((failure, errors)) => {
+            outcome.failure = Some(failure);
+            outcome.diagnostics = errors;
+        }
+    }
+
+    outcome
+}
+
+/// Like [`run_with`], but runs each statement as soon as it's parsed instead of parsing the whole input
+/// first, so no statement list is built for large scripts.
+///
+/// The input always runs as a program, and stops at the first error, whether a syntax or a runtime one:
+/// the statements before it have already run. With an output set on the interpreter, each statement's
+/// prints are flushed before the next statement is parsed, otherwise they end up in [`RunOutcome::stdout`].
+pub fn run_streaming(interpreter: &mut Interpreter, input: &str) -> RunOutcome {
+    let mut outcome = RunOutcome::default();
+
+    let start = Instant::now();
+    let mut scanner = scanner::Scanner::with_dialect(input.into(), interpreter.dialect());
+    scanner.scan_tokens();
+    outcome.stats.scan_time = start.elapsed();
+    if !scanner.errors().is_empty() {
+        outcome.diagnostics = scanner.errors().iter().cloned().map(Diagnostic::from).collect();
+        outcome.failure = Some(Failure::Compile);
+        return outcome;
+    }
+
+    let mut parser = Parser::new(scanner.tokens());
+    let mut strict = interpreter.dialect().strict;
+    let mut first = true;
+    interpreter.reset_steps();
+    interpreter.allocations = 0;
+    interpreter.statements_executed = 0;
+    loop {
+        let start = Instant::now();
+        let next = parser.peek_nth(0);
+        let (line, text_start) = (next.line, next.span.start);
+        let parsed = parser.next_statement();
+        outcome.stats.parse_time += start.elapsed();
+
+        let start = Instant::now();
+        let result = match parsed {
+            None => break,
+            Some(Err(error)) => Err((Failure::Compile, error.into())),
+            Some(Ok(stmt)) => {
+                let statements = std::slice::from_ref(&stmt);
+                // only a program's first statement can be the directive
+                strict = strict || (first && strict::has_directive(statements));
+                first = false;
+                let is_defined = |name: &str| interpreter.environment().contains(name);
+                match strict {
+                    true => strict::check(statements, is_defined).map_err(|err| (Failure::Compile, err.into())),
+                    false => Ok(()),
+                }
+                .and_then(|_| {
+                    let text = &input[text_start..parser.tokens[parser.current - 1].span.end];
+                    interpreter.enter_statement(line, text);
+                    // the statement's output is due now, not once the whole input has run
+                    interpreter
+                        .execute(&stmt)
+                        .and_then(|_| interpreter.flush_output())
+                        .map_err(|err| (Failure::Runtime, err.into()))
+                })
+            }
+        };
+        outcome.stats.eval_time += start.elapsed();
+        outcome.stats.expressions_evaluated = interpreter.steps_executed();
+
+        if let Err((failure, diagnostic)) = result {
+            outcome.failure = Some(failure);
+            outcome.diagnostics.push(diagnostic);
+            break;
+        }
+    }
+    outcome.stdout = interpreter.take_stdout();
+    outcome.stats.statements_executed = interpreter.statements_executed;
+    outcome.stats.allocations = interpreter.allocations;
+
+    outcome
+}
+
+/// What the input parsed to: a lone expression is evaluated for its value, anything else runs as a program.
+enum Parsed {
+    Expression(Expr),
+    /// The statements, and where each one is in the input.
+    Program(Vec<Stmt>, Vec<Location>),
+}
+
+/// A top-level statement's first line and the byte range of its text.
+type Location = (usize, Range<usize>);
+
+/// On failure, every error in the program is reported, not just the first.
+fn parse(parser: &mut Parser) -> Result<Parsed, Vec<ParserError>> {
+    let checkpoint = parser.checkpoint();
+    if let Ok(expr) = parser.expression() {
+        // with newline terminators the scanner ends the input with an implicit, empty `;`
+        let next = parser.peek_nth(0);
+        let implicit_end = next.token_type == TokenType::Semicolon
+            && next.lexeme().is_empty()
+            && parser.peek_nth(1).token_type == TokenType::Eof;
+        if parser.is_at_end() || implicit_end {
+            return Ok(Parsed::Expression(expr));
+        }
+    }
+
+    parser.rewind(checkpoint);
+    let mut statements = vec![];
+    let mut locations = vec![];
+    let mut errors = vec![];
+    loop {
+        let first = parser.peek_nth(0);
+        let (line, start) = (first.line, first.span.start);
+        match parser.next_statement() {
+            None => break,
+            Some(Ok(stmt)) => {
+                statements.push(stmt);
+                locations.push((line, start..parser.tokens[parser.current - 1].span.end));
+            }
+            Some(Err(error)) => errors.push(error),
+        }
+    }
+    if !errors.is_empty() {
+        return Err(errors);
+    }
+    Ok(Parsed::Program(statements, locations))
+}
+
+fn check_strict(interpreter: &Interpreter, parsed: &Parsed) -> Result<(), Diagnostic> {
+    let is_defined = |name: &str| interpreter.environment().contains(name);
+    let strict = interpreter.dialect().strict;
+
+    match parsed {
+        Parsed::Expression(expr) if strict => strict::check_expr(expr, is_defined)?,
+        Parsed::Program(statements, _) if strict || strict::has_directive(statements) => {
+            strict::check(statements, is_defined)?
+        }
+        _ => (),
+    }
+
+    Ok(())
+}
+
+fn execute(interpreter: &mut Interpreter, input: &str, parsed: &Parsed) -> Result<Option<IntrResult>, IntrError> {
+    match parsed {
+        Parsed::Expression(expr) => interpreter.evaluate(expr).map(Some),
+        Parsed::Program(statements, locations) => {
+            for (stmt, (line, text)) in statements.iter().zip(locations) {
+                interpreter.enter_statement(*line, &input[text.clone()]);
+                interpreter.execute(stmt)?;
+            }
+            Ok(None)
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{dialect::Dialect, messages::Message};
+    use std::{cell::RefCell, io, rc::Rc};
+
+    #[test]
+    fn test_run_value() {
+        let outcome = run("1 + 2");
+        assert_eq!(outcome.value, Some(IntrResult::Number(3.0)));
+        assert!(outcome.diagnostics.is_empty());
+        assert_eq!(outcome.failure, None);
+    }
+
+    #[test]
+    fn test_run_diagnostics() {
+        let outcome = run("1 + true");
+        assert_eq!(outcome.value, None);
+        assert_eq!(outcome.failure, Some(Failure::Runtime));
+        assert_eq!(outcome.diagnostics.len(), 1);
+        assert_eq!(outcome.diagnostics[0].line, Some(1));
+        assert_eq!(outcome.diagnostics[0].message, Message::OperandsMustBeNumbersOrStrings);
+
+        let outcome = run("1 +");
+        assert_eq!(outcome.failure, Some(Failure::Compile));
+        assert_eq!(outcome.diagnostics[0].line, Some(1));
+        assert_eq!(outcome.diagnostics[0].message, Message::ExpectExpression);
+
+        // every syntax error is placed at the token it was found at
+        let outcome = run("print 1;\nprint (2;\nvar = 3;\n\nprint 4");
+        let lines = outcome.diagnostics.iter().map(|d| d.line).collect::<Vec<_>>();
+        assert_eq!(lines, [Some(2), Some(3), Some(5)]);
+
+        let outcome = run("var a = \"\\q\";\nprint a;");
+        assert_eq!(
+            outcome.diagnostics[0].message,
+            Message::UnknownEscape { escape: "\\q".into() }
+        );
+        assert_eq!(outcome.diagnostics[0].line, Some(1));
+        assert_eq!(outcome.failure, Some(Failure::Compile));
+        assert!(outcome.stdout.is_empty());
+
+        // every parse error is reported, and nothing runs
+        let mut interpreter = Interpreter::default();
+        let outcome = run_with(&mut interpreter, "var a = 1; var = 2; print a; print;");
+        let messages = outcome.diagnostics.into_iter().map(|diagnostic| diagnostic.message);
+        assert_eq!(
+            messages.collect::<Vec<_>>(),
+            [Message::ExpectVariableName, Message::ExpectExpression]
+        );
+        assert!(outcome.stdout.is_empty());
+        assert!(!interpreter.environment().contains("a"));
+    }
+
+    #[test]
+    fn test_run_program() {
+        let mut interpreter = Interpreter::default();
+        let outcome = run_with(&mut interpreter, "var a = 1; a = a + 1;");
+        assert_eq!(outcome.value, None);
+        assert!(outcome.diagnostics.is_empty());
+
+        // bindings persist across runs on the same interpreter
+        let outcome = run_with(&mut interpreter, "a * 10");
+        assert_eq!(outcome.value, Some(IntrResult::Number(20.0)));
+
+        let outcome = run_with(&mut interpreter, "b = 1;");
+        assert_eq!(
+            outcome.diagnostics[0].message,
+            Message::UndefinedVariable { name: "b".into() }
+        );
+        assert_eq!(outcome.diagnostics[0].line, Some(1));
+    }
+
+    #[test]
+    fn test_run_streaming() {
+        let mut interpreter = Interpreter::default();
+        let outcome = run_streaming(&mut interpreter, "var a = 1;\nprint a;\na = a + 1;\n1 + 2;");
+        assert!(outcome.diagnostics.is_empty());
+        assert_eq!(outcome.stdout, "1\n");
+        assert_eq!(outcome.value, None);
+
+        // statements before the error have run, and nothing after it
+        let outcome = run_streaming(&mut interpreter, "print a;\nvar = 2;\nprint 3;");
+        assert_eq!(outcome.stdout, "2\n");
+        assert_eq!(outcome.diagnostics.len(), 1);
+        assert_eq!(outcome.diagnostics[0].message, Message::ExpectVariableName);
+        assert_eq!(outcome.failure, Some(Failure::Compile));
+
+        let outcome = run_streaming(&mut interpreter, "print -nil;\nvar = 2;");
+        assert_eq!(outcome.diagnostics.len(), 1);
+        assert_eq!(outcome.diagnostics[0].message, Message::OperandMustBeNumber);
+        assert_eq!(outcome.failure, Some(Failure::Runtime));
+
+        // the directive is honoured, and later declarations are seen by the checks after them
+        let outcome = run_streaming(&mut interpreter, "\"use strict\";\nvar b;\nb = 1;\nprint b;\nc = 2;");
+        assert_eq!(outcome.stdout, "1\n");
+        assert_eq!(
+            outcome.diagnostics[0].message,
+            Message::UndeclaredAssignment { name: "c".into() }
+        );
+    }
+
+    #[test]
+    fn test_run_streaming_flushes_each_statement() {
+        /// Holds writes back until flushed, recording what each flush let through.
+        struct Buffered {
+            pending: Vec<u8>,
+            flushed: Rc<RefCell<Vec<String>>>,
+        }
+        impl io::Write for Buffered {
+            fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+                self.pending.write(buf)
+            }
+            fn flush(&mut self) -> io::Result<()> {
+                let text = String::from_utf8(std::mem::take(&mut self.pending)).unwrap();
+                self.flushed.borrow_mut().push(text);
+                Ok(())
+            }
+        }
+
+        let flushed = Rc::new(RefCell::new(Vec::new()));
+        let mut interpreter = Interpreter::builder()
+            .output(Buffered {
+                pending: Vec::new(),
+                flushed: flushed.clone(),
+            })
+            .build();
+        let outcome = run_streaming(&mut interpreter, "print 1;\nvar a = 2;\nprint a;\nprint -nil;");
+        assert_eq!(*flushed.borrow(), ["1\n", "", "2\n"]);
+        assert_eq!(outcome.stdout, "");
+        assert_eq!(outcome.failure, Some(Failure::Runtime));
+    }
+
+    #[test]
+    fn test_on_statement() {
+        let entered = Rc::new(RefCell::new(Vec::new()));
+        let hook = {
+            let entered = entered.clone();
+            move |line: usize, text: &str| entered.borrow_mut().push((line, text.to_string()))
+        };
+        let mut interpreter = Interpreter::builder().on_statement(hook).build();
+        let source = "var a = 1;\n// note\nprint\n  a;\n-nil;\nprint 2;";
+
+        run_with(&mut interpreter, source);
+        let expected = [(1, "var a = 1;"), (3, "print\n  a;"), (5, "-nil;")];
+        let expected = expected.map(|(line, text)| (line, text.to_string()));
+        assert_eq!(*entered.borrow(), expected);
+
+        entered.borrow_mut().clear();
+        run_streaming(&mut interpreter, source);
+        assert_eq!(*entered.borrow(), expected);
+
+        // a lone expression isn't a statement
+        entered.borrow_mut().clear();
+        run_with(&mut interpreter, "a + 1");
+        assert!(entered.borrow().is_empty());
+    }
+
+    #[test]
+    fn test_run_print() {
+        let outcome = run("var a = \"one\";\nprint a + \" two\";\nprint 1 + 2;\nprint nil;");
+        assert_eq!(outcome.stdout, "one two\n3\nnil\n");
+        assert_eq!(outcome.value, None);
+
+        // output printed before a runtime error is kept
+        let outcome = run("print 1;\nprint -\"a\";");
+        assert_eq!(outcome.stdout, "1\n");
+        assert_eq!(outcome.diagnostics[0].line, Some(2));
+
+        let outcome = run("print 1");
+        assert_eq!(outcome.diagnostics[0].message, Message::ExpectSemicolonAfterValue);
+    }
+
+    #[test]
+    fn test_run_strict() {
+        // the directive rejects the program before anything runs
+        let mut interpreter = Interpreter::default();
+        let outcome = run_with(&mut interpreter, "\"use strict\";\nvar a = 1;\nb = 2;");
+        assert_eq!(
+            outcome.diagnostics[0].message,
+            Message::UndeclaredAssignment { name: "b".into() }
+        );
+        assert_eq!(outcome.diagnostics[0].line, Some(3));
+        assert!(!interpreter.environment().contains("a"));
+
+        let dialect = Dialect {
+            strict: true,
+            ..Default::default()
+        };
+        let mut interpreter = Interpreter::builder().dialect(dialect).build();
+        assert!(!run_with(&mut interpreter, "b = 2").diagnostics.is_empty());
+        assert!(run_with(&mut interpreter, "var b;").diagnostics.is_empty());
+        assert_eq!(run_with(&mut interpreter, "b = 2").value, Some(IntrResult::Number(2.0)));
+    }
+
+    #[test]
+    fn test_run_newline_terminators() {
+        let dialect = Dialect {
+            newline_terminators: true,
+            ..Default::default()
+        };
+        let mut interpreter = Interpreter::builder().dialect(dialect).build();
+        assert_eq!(run_with(&mut interpreter, "1 + 2").value, Some(IntrResult::Number(3.0)));
+        assert_eq!(
+            run_with(&mut interpreter, "1 + 2\n").value,
+            Some(IntrResult::Number(3.0))
+        );
+
+        // an explicit `;` or a second line makes it a program
+        assert_eq!(run_with(&mut interpreter, "1 + 2;").value, None);
+        let outcome = run_with(&mut interpreter, "var a = 1\nprint a + 2");
+        assert_eq!(outcome.value, None);
+        assert_eq!(outcome.stdout, "3\n");
+    }
+
+    #[test]
+    fn test_run_stats() {
+        let outcome = run("(1 + 2) * 3");
+        assert_eq!(outcome.stats.statements_executed, 0);
+        assert_eq!(outcome.stats.expressions_evaluated, 6);
+        assert_eq!(outcome.stats.allocations, 0);
+
+        // literals share the scanned text, only the concatenation allocates
+        let outcome = run("\"a\" + \"b\"");
+        assert_eq!(outcome.stats.expressions_evaluated, 3);
+        assert_eq!(outcome.stats.allocations, 1);
+
+        // strings are shared, so copying one around doesn't allocate
+        let mut interpreter = Interpreter::default();
+        run_with(&mut interpreter, "var s = \"text\";");
+        let outcome = run_with(&mut interpreter, "var t = s; var u = t; print u == s;");
+        assert_eq!(outcome.stdout, "true\n");
+        assert_eq!(outcome.stats.statements_executed, 3);
+        assert_eq!(outcome.stats.allocations, 0);
+
+        // a runtime error stops at the statement that failed, which still counts
+        let outcome = run_streaming(&mut interpreter, "print 1;\nprint -nil;\nprint 3;");
+        assert_eq!(outcome.stats.statements_executed, 2);
+    }
+}
@@ -1,79 +1,453 @@
-mod ast;
-mod error;
-mod interpreter;
-mod parser;
-mod scanner;
-mod token;
-
-use error::LoxError;
-use interpreter::{Interpreter, IntrError, IntrResult};
-use parser::Parser;
-use std::io::{self, BufRead};
-
-fn main() -> anyhow::Result<()> {
+mod crash;
+mod output;
+
+use crash::Context;
+use lox::{
+    dialect::Dialect,
+    error::Diagnostic,
+    interpreter::{Interpreter, InterpreterBuilder, IntrResult},
+    messages::{Lang, Message},
+    parser::Parser,
+    scanner::Scanner,
+    Failure,
+};
+use output::{Buffering, Output};
+use std::{
+    io::{self, BufRead, IsTerminal},
+    path::Path,
+    process::ExitCode,
+};
+
+/// Exit statuses from sysexits.h, as jlox uses them.
+const EX_USAGE: u8 = 64;
+const EX_DATAERR: u8 = 65;
+const EX_NOINPUT: u8 = 66;
+const EX_SOFTWARE: u8 = 70;
+/// Anything else that went wrong, e.g. stale outputs found by `run-md --check`.
+const EX_FAILURE: u8 = 1;
+
+/// Ends the process with a status; whatever caused it was already reported.
+#[derive(Debug, thiserror::Error)]
+#[error("exit status {0}")]
+struct Exit(u8);
+
+#[derive(Default)]
+struct Options {
+    /// Print execution statistics after each run.
+    stats: bool,
+    /// Language of diagnostics.
+    lang: Lang,
+    buffering: Buffering,
+    /// Escape non-ASCII output.
+    ascii: bool,
+    dialect: Dialect,
+    /// Decimal places `print` shows numbers with.
+    precision: Option<usize>,
+    /// Run each statement as soon as it's parsed, see [`lox::run_streaming`].
+    stream: bool,
+    /// Print the scanned tokens instead of running anything.
+    tokens: bool,
+    /// Print the parsed tree instead of running anything.
+    ast: bool,
+}
+
+fn main() -> ExitCode {
     let args = std::env::args().collect::<Vec<String>>();
-    println!("Args: {:?}", args);
-    match args.len() {
-        1 => run_prompt(),
-        2 => run_file(args[1].as_str()),
-        _ => help(),
+    match crash::guard(&args, || cli(args.clone())) {
+        Some(Ok(())) => ExitCode::SUCCESS,
+        Some(Err(err)) => match err.downcast_ref::<Exit>() {
+            Some(Exit(status)) => ExitCode::from(*status),
+            None => {
+                eprintln!("Error: {:?}", err);
+                ExitCode::FAILURE
+            }
+        },
+        None => ExitCode::from(EX_SOFTWARE),
     }
 }
 
-fn run_prompt() -> anyhow::Result<()> {
-    let lines = io::stdin().lock().lines();
-    for line in lines {
-        if let Ok(line) = line {
-            if line.is_empty() {
-                break;
+fn cli(mut args: Vec<String>) -> anyhow::Result<()> {
+    let mut options = Options::default();
+    let mut invalid = None;
+    args.retain(|arg| match arg.as_str() {
+        "--stats" => {
+            options.stats = true;
+            false
+        }
+        "--strict" => {
+            options.dialect.strict = true;
+            false
+        }
+        "--newline-terminators" => {
+            options.dialect.newline_terminators = true;
+            false
+        }
+        "--ascii" => {
+            options.ascii = true;
+            false
+        }
+        "--stream" => {
+            options.stream = true;
+            false
+        }
+        "--tokens" => {
+            options.tokens = true;
+            false
+        }
+        "--ast" => {
+            options.ast = true;
+            false
+        }
+        arg if arg.starts_with("--buffering=") => {
+            match arg["--buffering=".len()..].parse() {
+                Ok(buffering) => options.buffering = buffering,
+                Err(err) => invalid = Some(err),
             }
-            run(&line)
+            false
         }
+        arg if arg.starts_with("--precision=") => {
+            let digits = &arg["--precision=".len()..];
+            match digits.parse() {
+                Ok(digits) => options.precision = Some(digits),
+                Err(_) => invalid = Some(Message::InvalidPrecision { digits: digits.into() }),
+            }
+            false
+        }
+        arg if arg.starts_with("--lang=") => {
+            match arg["--lang=".len()..].parse() {
+                Ok(lang) => options.lang = lang,
+                Err(err) => invalid = Some(err),
+            }
+            false
+        }
+        _ => true,
+    });
+    if let Some(err) = invalid {
+        report_error(err, options.lang);
+        return Err(Exit(EX_USAGE).into());
+    }
+    match args.iter().map(String::as_str).collect::<Vec<_>>()[..] {
+        [_] => run_prompt(&options),
+        [_, "init"] => init("."),
+        [_, "init", path] => init(path),
+        [_, "render", template] => render(template, &options),
+        [_, "run-md", path] => run_markdown(path, false, &options),
+        [_, "run-md", "--check", path] => run_markdown(path, true, &options),
+        [_, "-e" | "--eval", code] => eval(code, &options),
+        // not a script named `-e`
+        [_, "-e" | "--eval"] => help(),
+        [_, filename] => run_file(filename, &options),
+        _ => help(),
     }
-    Ok(())
 }
 
-fn run_file(filename: &str) -> anyhow::Result<()> {
-    let source = std::fs::read_to_string(filename).expect("Could not read file");
-    run(&source);
+fn init(path: &str) -> anyhow::Result<()> {
+    for file in lox::project::init(Path::new(path))? {
+        println!("Created {}", file.display());
+    }
     Ok(())
 }
 
-fn help() -> anyhow::Result<()> {
-    println!("Usage: rlox [script]");
+fn render(filename: &str, options: &Options) -> anyhow::Result<()> {
+    let template = read_input(filename, options)?;
+    crash::set_context(Context {
+        file: Some(filename.into()),
+        line: 1,
+        source: template.clone(),
+        statement: None,
+    });
+    let mut output = Output::new(options.buffering, options.ascii);
+    let mut interpreter = interpreter(options).build();
+    match lox::template::render(&mut interpreter, &template) {
+        Ok(text) => {
+            output.write(&text)?;
+            output.flush()?;
+            Ok(())
+        }
+        Err((failure, diagnostic)) => {
+            eprintln!("{}", diagnostic.render(options.lang));
+            Err(Exit(exit_status(failure)).into())
+        }
+    }
+}
+
+/// Updates the outputs of every code block in place, or with `check` only reports the stale ones.
+fn run_markdown(filename: &str, check: bool, options: &Options) -> anyhow::Result<()> {
+    let markdown = read_input(filename, options)?;
+    crash::set_context(Context {
+        file: Some(filename.into()),
+        line: 1,
+        source: markdown.clone(),
+        statement: None,
+    });
+    let mut interpreter = interpreter(options).build();
+    let notebook = lox::notebook::run(&mut interpreter, &markdown, options.lang);
+
+    if !check {
+        if notebook.markdown != markdown {
+            std::fs::write(filename, notebook.markdown)?;
+        }
+        return Ok(());
+    }
+
+    let stale = notebook
+        .cells
+        .iter()
+        .filter(|cell| !cell.is_up_to_date())
+        .collect::<Vec<_>>();
+    for cell in &stale {
+        let differs = Message::OutputDiffers {
+            file: filename.into(),
+            line: cell.line,
+        };
+        println!("{}", differs.render(options.lang));
+        print!("{}", cell.output);
+    }
+    if !stale.is_empty() {
+        let message = Message::StaleBlocks {
+            stale: stale.len(),
+            total: notebook.cells.len(),
+        };
+        report_error(message, options.lang);
+        return Err(Exit(EX_FAILURE).into());
+    }
     Ok(())
 }
 
-fn run(source: &str) {
-    let res = interpret(source);
-    match res {
-        Ok(res) => println!("{:?}", res),
-        Err(err) => match err {
-            LoxError::ParseError(_) => todo!(),
-            LoxError::RuntimeError(IntrError::Unsupported(token)) => {
-                println!("Unsupported operation\n[line {}]", token.line)
+fn run_prompt(options: &Options) -> anyhow::Result<()> {
+    let mut output = Output::new(options.buffering, options.ascii);
+    let mut interpreter = interpreter(options)
+        .output(output.clone())
+        .on_statement(crash::set_statement)
+        .build();
+    // prompts would only clutter piped output
+    let interactive = io::stdin().is_terminal();
+    let prompt = |output: &mut Output, prompt: &str| match interactive {
+        true => output.write(prompt).and_then(|_| output.flush()),
+        false => Ok(()),
+    };
+
+    // lines of a statement that isn't complete yet, starting at line `first`
+    let mut source = String::new();
+    let mut first = 1;
+    prompt(&mut output, "> ")?;
+    for (number, line) in io::stdin().lock().lines().map_while(Result::ok).enumerate() {
+        if source.is_empty() {
+            if line.trim().is_empty() {
+                prompt(&mut output, "> ")?;
+                continue;
             }
-            LoxError::RuntimeError(IntrError::Runtime(token, message)) => {
-                println!("{}\n[line {}]", message, token.line)
+            // `:complete <prefix>` lists the globals and keywords a name starting with `prefix` could be
+            if let Some(prefix) = line.strip_prefix(":complete") {
+                for name in interpreter.completions(prefix.trim()) {
+                    output.write_line(name)?;
+                }
+                prompt(&mut output, "> ")?;
+                continue;
             }
-            _ => todo!(),
-        },
+            first = number + 1;
+        } else {
+            source.push('\n');
+        }
+        source.push_str(&line);
+
+        let mut scanner = Scanner::with_dialect(source.clone(), options.dialect);
+        scanner.scan_tokens();
+        if scanner.is_incomplete() {
+            prompt(&mut output, "... ")?;
+            continue;
+        }
+
+        run_entry(&mut interpreter, &source, first, options, &mut output)?;
+        source.clear();
+        prompt(&mut output, "> ")?;
     }
+    // input ended partway through a statement, report whatever is wrong with it
+    if !source.is_empty() {
+        run_entry(&mut interpreter, &source, first, options, &mut output)?;
+    }
+    output.flush()?;
+    Ok(())
 }
 
-fn interpret(input: &str) -> Result<IntrResult, LoxError> {
-    let mut scanner = scanner::Scanner::new(input.into());
-    let tokens = scanner.scan_tokens();
+fn run_entry(
+    interpreter: &mut Interpreter,
+    source: &str,
+    line: usize,
+    options: &Options,
+    output: &mut Output,
+) -> io::Result<()> {
+    crash::set_context(Context {
+        file: None,
+        line,
+        source: source.into(),
+        statement: None,
+    });
+    // a failed entry doesn't end the session
+    run(interpreter, source, options, Interpreter::repr, output).map(|_| ())
+}
 
-    let mut parser = Parser::new(tokens);
-    let mut interpreter = Interpreter;
+/// Reads an input file, failing with EX_NOINPUT if it can't be read.
+fn read_input(filename: &str, options: &Options) -> anyhow::Result<String> {
+    std::fs::read_to_string(filename).map_err(|err| {
+        // the OS's reason isn't translated
+        let message = Message::Error {
+            message: format!(
+                "{}: {}",
+                Message::UnreadableFile { file: filename.into() }.render(options.lang),
+                err
+            ),
+        };
+        eprintln!("{}", message.render(options.lang));
+        Exit(EX_NOINPUT).into()
+    })
+}
 
-    let res = match parser.expression() {
-        Ok(expr) => interpreter.evaluate(&expr)?,
-        Err(err) => {
-            return Err(err.into());
-        }
+/// Prints a problem with the command line or its inputs, rather than with the program, to stderr.
+fn report_error(message: Message, lang: Lang) {
+    let message = Message::Error {
+        message: message.render(lang),
+    };
+    eprintln!("{}", message.render(lang));
+}
+
+fn run_file(filename: &str, options: &Options) -> anyhow::Result<()> {
+    let source = read_input(filename, options)?;
+    crash::set_context(Context {
+        file: Some(filename.into()),
+        line: 1,
+        source: source.clone(),
+        statement: None,
+    });
+    run_program(&source, options)
+}
+
+/// Runs a snippet given on the command line, printing its value if it's an expression.
+fn eval(code: &str, options: &Options) -> anyhow::Result<()> {
+    crash::set_context(Context {
+        file: Some("-e".into()),
+        line: 1,
+        source: code.into(),
+        statement: None,
+    });
+    run_program(code, options)
+}
+
+/// Runs a whole program in a fresh interpreter, failing with the exit status for how it went wrong.
+fn run_program(source: &str, options: &Options) -> anyhow::Result<()> {
+    let mut output = Output::new(options.buffering, options.ascii);
+    let mut interpreter = interpreter(options)
+        .output(output.clone())
+        .on_statement(crash::set_statement)
+        .build();
+    let failure = run(&mut interpreter, source, options, Interpreter::stringify, &mut output)?;
+    output.flush()?;
+    match failure {
+        Some(failure) => Err(Exit(exit_status(failure)).into()),
+        None => Ok(()),
+    }
+}
+
+fn exit_status(failure: Failure) -> u8 {
+    match failure {
+        Failure::Compile => EX_DATAERR,
+        Failure::Runtime => EX_SOFTWARE,
+    }
+}
+
+fn interpreter(options: &Options) -> InterpreterBuilder {
+    let builder = Interpreter::builder().dialect(options.dialect);
+    match options.precision {
+        Some(digits) => builder.precision(digits),
+        None => builder,
+    }
+}
+
+fn help() -> anyhow::Result<()> {
+    println!("Usage: rlox [--stats] [--lang=en|ru] [--buffering=line|full|none] [--ascii] [--strict] [--newline-terminators] [--precision=N] [--stream] [--tokens] [--ast] [script]");
+    println!("       rlox -e|--eval <code>");
+    println!("       rlox init [path]");
+    println!("       rlox render <template>");
+    println!("       rlox run-md [--check] <notes.md>");
+    Err(Exit(EX_USAGE).into())
+}
+
+/// Runs `source` and reports the outcome, showing a lone expression's value with `show`, and returns how it
+/// failed, if it did.
+fn run(
+    interpreter: &mut Interpreter,
+    source: &str,
+    options: &Options,
+    show: fn(&Interpreter, &IntrResult) -> String,
+    output: &mut Output,
+) -> io::Result<Option<Failure>> {
+    if options.tokens {
+        return dump_tokens(source, options, output);
+    }
+    if options.ast {
+        return dump_ast(source, options, output);
+    }
+
+    let outcome = match options.stream {
+        true => lox::run_streaming(interpreter, source),
+        false => lox::run_with(interpreter, source),
     };
+    // `print` already wrote through the interpreter's output, only a lone expression's value is left
+    if let Some(value) = outcome.value {
+        output.write_line(&show(interpreter, &value))?;
+    }
+    for diagnostic in outcome.diagnostics {
+        output.write_line(&diagnostic.render(options.lang))?;
+    }
+    if options.stats {
+        output.flush()?;
+        eprintln!("{}", outcome.stats);
+    }
+    Ok(outcome.failure)
+}
+
+/// Prints one token per line: its line, type, lexeme and literal.
+fn dump_tokens(source: &str, options: &Options, output: &mut Output) -> io::Result<Option<Failure>> {
+    let mut scanner = Scanner::with_dialect(source.into(), options.dialect);
+    for token in scanner.scan_tokens() {
+        output.write_line(&format!("{:>4} {}", token.line, token))?;
+    }
+    for error in scanner.errors() {
+        output.write_line(&Diagnostic::from(error.clone()).render(options.lang))?;
+    }
+
+    Ok((!scanner.errors().is_empty()).then_some(Failure::Compile))
+}
+
+/// Prints the tree the input parses to: a lone expression as is, a program one statement per line.
+fn dump_ast(source: &str, options: &Options, output: &mut Output) -> io::Result<Option<Failure>> {
+    let mut scanner = Scanner::with_dialect(source.into(), options.dialect);
+    scanner.scan_tokens();
+    let mut diagnostics = scanner
+        .errors()
+        .iter()
+        .cloned()
+        .map(Diagnostic::from)
+        .collect::<Vec<_>>();
+
+    if diagnostics.is_empty() {
+        let mut parser = Parser::new(scanner.tokens());
+        let checkpoint = parser.checkpoint();
+        match parser.expression() {
+            Ok(expr) if parser.is_at_end() => output.write_line(&expr.to_string())?,
+            _ => {
+                parser.rewind(checkpoint);
+                let program = parser.parse();
+                for stmt in &program.statements {
+                    output.write_line(&stmt.to_string())?;
+                }
+                diagnostics.extend(program.errors.into_iter().map(Diagnostic::from));
+            }
+        }
+    }
+    for diagnostic in &diagnostics {
+        output.write_line(&diagnostic.render(options.lang))?;
+    }
 
-    Ok(res)
+    Ok((!diagnostics.is_empty()).then_some(Failure::Compile))
 }
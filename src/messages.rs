@@ -0,0 +1,343 @@
+//! Catalog of user-facing diagnostic messages.
+//!
+//! Errors carry a [`Message`] instead of a formatted string, and are rendered in the requested [`Lang`]
+//! only when reported. Templates reference arguments by name, e.g. `{operator}`.
+
+use std::{fmt::Display, str::FromStr};
+
+#[derive(Debug, Default, Clone, Copy, PartialEq)]
+pub enum Lang {
+    #[default]
+    En,
+    Ru,
+}
+
+impl FromStr for Lang {
+    type Err = Message;
+
+    fn from_str(value: &str) -> Result<Self, Self::Err> {
+        match value {
+            "en" => Ok(Lang::En),
+            "ru" => Ok(Lang::Ru),
+            _ => Err(Message::UnsupportedLang { value: value.into() }),
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum Message {
+    ExpectExpression,
+    ExpectRightParen,
+    ChainedComparison { first: String, second: String },
+    MissingLeftOperand { operator: String },
+    TooDeeplyNested,
+    OperandMustBeNumber,
+    OperandsMustBeNumbers,
+    OperandsMustBeNumbersOrStrings,
+    ExpectSemicolonAfterExpression,
+    ExpectSemicolonAfterVariable,
+    ExpectSemicolonAfterValue,
+    ExpectVariableName,
+    InvalidAssignmentTarget,
+    ThisOutsideClass,
+    BreakOutsideLoop,
+    UndefinedVariable { name: String },
+    UndeclaredAssignment { name: String },
+    UnterminatedTag { tag: String },
+    UnknownEscape { escape: String },
+    InvalidNumber { literal: String },
+    StepLimit { max_steps: usize },
+    OutputFailed { error: String },
+    Line { line: usize },
+    Error { message: String },
+    UnsupportedLang { value: String },
+    UnsupportedBuffering { value: String },
+    InvalidPrecision { digits: String },
+    UnreadableFile { file: String },
+    OutputDiffers { file: String, line: usize },
+    StaleBlocks { stale: usize, total: usize },
+}
+
+/// `(key, English, Russian)`
+const CATALOG: &[(&str, &str, &str)] = &[
+    ("expect_expression", "Expect expression.", "Ожидалось выражение."),
+    (
+        "expect_right_paren",
+        "Expect ')' after expression.",
+        "Ожидалась ')' после выражения.",
+    ),
+    (
+        "chained_comparison",
+        "Comparisons can't be chained: 'a {first} b {second} c' compares the result of 'a {first} b' with 'c'.",
+        "Сравнения нельзя объединять в цепочку: 'a {first} b {second} c' сравнивает результат 'a {first} b' с 'c'.",
+    ),
+    (
+        "missing_left_operand",
+        "Binary operator '{operator}' requires a left-hand operand.",
+        "Бинарному оператору '{operator}' нужен левый операнд.",
+    ),
+    (
+        "too_deeply_nested",
+        "Expression too deeply nested.",
+        "Слишком глубокая вложенность выражения.",
+    ),
+    (
+        "operand_must_be_number",
+        "Operand must be a number.",
+        "Операнд должен быть числом.",
+    ),
+    (
+        "operands_must_be_numbers",
+        "Operands must be numbers.",
+        "Операнды должны быть числами.",
+    ),
+    (
+        "operands_must_be_numbers_or_strings",
+        "Operands must be two numbers or two strings.",
+        "Операнды должны быть двумя числами или двумя строками.",
+    ),
+    (
+        "expect_semicolon_after_expression",
+        "Expect ';' after expression.",
+        "Ожидалась ';' после выражения.",
+    ),
+    (
+        "expect_semicolon_after_variable",
+        "Expect ';' after variable declaration.",
+        "Ожидалась ';' после объявления переменной.",
+    ),
+    (
+        "expect_semicolon_after_value",
+        "Expect ';' after value.",
+        "Ожидалась ';' после значения.",
+    ),
+    (
+        "expect_variable_name",
+        "Expect variable name.",
+        "Ожидалось имя переменной.",
+    ),
+    (
+        "invalid_assignment_target",
+        "Invalid assignment target.",
+        "Недопустимая цель присваивания.",
+    ),
+    (
+        "this_outside_class",
+        "Can't use 'this' outside of a class.",
+        "Нельзя использовать 'this' вне класса.",
+    ),
+    (
+        "break_outside_loop",
+        "Can't use 'break' outside of a loop.",
+        "Нельзя использовать 'break' вне цикла.",
+    ),
+    (
+        "undefined_variable",
+        "Undefined variable '{name}'.",
+        "Неопределённая переменная '{name}'.",
+    ),
+    (
+        "undeclared_assignment",
+        "Assignment to undeclared variable '{name}'; declare it with 'var' first.",
+        "Присваивание необъявленной переменной '{name}'; сначала объявите её через 'var'.",
+    ),
+    (
+        "unterminated_tag",
+        "Unterminated '{tag}' tag.",
+        "Незакрытый тег '{tag}'.",
+    ),
+    (
+        "unknown_escape",
+        "Unknown escape sequence '{escape}'.",
+        "Неизвестная escape-последовательность '{escape}'.",
+    ),
+    (
+        "invalid_number",
+        "Invalid number literal '{literal}'.",
+        "Некорректное числовое значение '{literal}'.",
+    ),
+    (
+        "step_limit",
+        "Step limit of {max_steps} exceeded.",
+        "Превышен лимит шагов: {max_steps}.",
+    ),
+    (
+        "output_failed",
+        "Could not write output: {error}.",
+        "Не удалось записать вывод: {error}.",
+    ),
+    ("line", "[line {line}]", "[строка {line}]"),
+    // problems with the command line rather than the program, prefixed by `error`
+    ("error", "Error: {message}", "Ошибка: {message}"),
+    (
+        "unsupported_lang",
+        "Unsupported language '{value}', expected 'en' or 'ru'.",
+        "Неподдерживаемый язык '{value}', ожидался 'en' или 'ru'.",
+    ),
+    (
+        "unsupported_buffering",
+        "Unsupported buffering '{value}', expected 'line', 'full' or 'none'.",
+        "Неподдерживаемая буферизация '{value}', ожидалась 'line', 'full' или 'none'.",
+    ),
+    (
+        "invalid_precision",
+        "Invalid precision '{digits}', expected a number of digits.",
+        "Некорректная точность '{digits}', ожидалось число знаков.",
+    ),
+    (
+        "unreadable_file",
+        "Could not read '{file}'",
+        "Не удалось прочитать '{file}'",
+    ),
+    (
+        "output_differs",
+        "{file}:{line}: output differs",
+        "{file}:{line}: вывод отличается",
+    ),
+    (
+        "stale_blocks",
+        "{stale} of {total} blocks are out of date",
+        "Устаревших блоков: {stale} из {total}",
+    ),
+];
+
+impl Message {
+    pub fn key(&self) -> &'static str {
+        match self {
+            Message::ExpectExpression => "expect_expression",
+            Message::ExpectRightParen => "expect_right_paren",
+            Message::ChainedComparison { .. } => "chained_comparison",
+            Message::MissingLeftOperand { .. } => "missing_left_operand",
+            Message::TooDeeplyNested => "too_deeply_nested",
+            Message::OperandMustBeNumber => "operand_must_be_number",
+            Message::OperandsMustBeNumbers => "operands_must_be_numbers",
+            Message::OperandsMustBeNumbersOrStrings => "operands_must_be_numbers_or_strings",
+            Message::ExpectSemicolonAfterExpression => "expect_semicolon_after_expression",
+            Message::ExpectSemicolonAfterVariable => "expect_semicolon_after_variable",
+            Message::ExpectSemicolonAfterValue => "expect_semicolon_after_value",
+            Message::ExpectVariableName => "expect_variable_name",
+            Message::InvalidAssignmentTarget => "invalid_assignment_target",
+            Message::ThisOutsideClass => "this_outside_class",
+            Message::BreakOutsideLoop => "break_outside_loop",
+            Message::UndefinedVariable { .. } => "undefined_variable",
+            Message::UndeclaredAssignment { .. } => "undeclared_assignment",
+            Message::UnterminatedTag { .. } => "unterminated_tag",
+            Message::UnknownEscape { .. } => "unknown_escape",
+            Message::InvalidNumber { .. } => "invalid_number",
+            Message::StepLimit { .. } => "step_limit",
+            Message::OutputFailed { .. } => "output_failed",
+            Message::Line { .. } => "line",
+            Message::Error { .. } => "error",
+            Message::UnsupportedLang { .. } => "unsupported_lang",
+            Message::UnsupportedBuffering { .. } => "unsupported_buffering",
+            Message::InvalidPrecision { .. } => "invalid_precision",
+            Message::UnreadableFile { .. } => "unreadable_file",
+            Message::OutputDiffers { .. } => "output_differs",
+            Message::StaleBlocks { .. } => "stale_blocks",
+        }
+    }
+
+    fn args(&self) -> Vec<(&'static str, String)> {
+        match self {
+            Message::ChainedComparison { first, second } => vec![("first", first.clone()), ("second", second.clone())],
+            Message::MissingLeftOperand { operator } => vec![("operator", operator.clone())],
+            Message::UndefinedVariable { name } | Message::UndeclaredAssignment { name } => {
+                vec![("name", name.clone())]
+            }
+            Message::UnterminatedTag { tag } => vec![("tag", tag.clone())],
+            Message::UnknownEscape { escape } => vec![("escape", escape.clone())],
+            Message::InvalidNumber { literal } => vec![("literal", literal.clone())],
+            Message::StepLimit { max_steps } => vec![("max_steps", max_steps.to_string())],
+            Message::OutputFailed { error } => vec![("error", error.clone())],
+            Message::Line { line } => vec![("line", line.to_string())],
+            Message::Error { message } => vec![("message", message.clone())],
+            Message::UnsupportedLang { value } | Message::UnsupportedBuffering { value } => {
+                vec![("value", value.clone())]
+            }
+            Message::InvalidPrecision { digits } => vec![("digits", digits.clone())],
+            Message::UnreadableFile { file } => vec![("file", file.clone())],
+            Message::OutputDiffers { file, line } => vec![("file", file.clone()), ("line", line.to_string())],
+            Message::StaleBlocks { stale, total } => vec![("stale", stale.to_string()), ("total", total.to_string())],
+            _ => vec![],
+        }
+    }
+
+    pub fn render(&self, lang: Lang) -> String {
+        let (_, en, ru) = CATALOG
+            .iter()
+            .find(|(key, _, _)| *key == self.key())
+            .expect("every message has a catalog entry");
+        let template = match lang {
+            Lang::En => en,
+            Lang::Ru => ru,
+        };
+
+        self.args()
+            .into_iter()
+            .fold(template.to_string(), |text, (name, value)| {
+                text.replace(&format!("{{{}}}", name), &value)
+            })
+    }
+}
+
+impl Display for Message {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", self.render(Lang::En))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_render() {
+        let message = Message::MissingLeftOperand { operator: "*".into() };
+        assert_eq!(
+            message.render(Lang::En),
+            "Binary operator '*' requires a left-hand operand."
+        );
+        assert_eq!(message.render(Lang::Ru), "Бинарному оператору '*' нужен левый операнд.");
+
+        let message = Message::ChainedComparison {
+            first: "<".into(),
+            second: ">".into(),
+        };
+        assert_eq!(
+            message.to_string(),
+            "Comparisons can't be chained: 'a < b > c' compares the result of 'a < b' with 'c'."
+        );
+    }
+
+    #[test]
+    fn test_catalog_is_complete() {
+        for (key, en, ru) in CATALOG {
+            assert_eq!(
+                CATALOG.iter().filter(|(other, _, _)| other == key).count(),
+                1,
+                "{}",
+                key
+            );
+            // both languages use the same placeholders
+            let placeholders = |text: &str| {
+                let mut names = text
+                    .split('{')
+                    .skip(1)
+                    .map(|s| s.split('}').next().unwrap().to_string())
+                    .collect::<Vec<_>>();
+                names.sort();
+                names
+            };
+            assert_eq!(placeholders(en), placeholders(ru), "{}", key);
+        }
+    }
+
+    #[test]
+    fn test_lang_from_str() {
+        assert_eq!("ru".parse(), Ok(Lang::Ru));
+        assert_eq!(
+            "de".parse::<Lang>(),
+            Err(Message::UnsupportedLang { value: "de".into() })
+        );
+    }
+}
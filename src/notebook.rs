@@ -0,0 +1,171 @@
+//! Markdown notebooks: ```` ```lox ```` blocks are run in order in one interpreter session, and what each
+//! printed is written into an ```` ```output ```` block right after it.
+//!
+//! An existing output block is replaced, so running a notebook again is a no-op unless the code changed,
+//! and comparing the outputs with the ones on disk checks the document like a doctest.
+
+use crate::{interpreter::Interpreter, messages::Lang, run_with};
+
+const FENCE: &str = "```";
+const CODE_FENCE: &str = "```lox";
+const OUTPUT_FENCE: &str = "```output";
+
+/// A code block and what running it produced.
+#[derive(Debug, PartialEq)]
+pub struct Cell {
+    /// Line of the opening fence.
+    pub line: usize,
+    pub output: String,
+    /// Contents of the output block that followed the code, if there was one.
+    pub expected: Option<String>,
+}
+
+impl Cell {
+    /// Whether the document already showed this output; a missing output block stands for no output.
+    pub fn is_up_to_date(&self) -> bool {
+        self.expected.as_deref().unwrap_or_default() == self.output
+    }
+}
+
+#[derive(Debug)]
+pub struct Notebook {
+    /// The document with every output block brought up to date.
+    pub markdown: String,
+    pub cells: Vec<Cell>,
+}
+
+pub fn run(interpreter: &mut Interpreter, markdown: &str, lang: Lang) -> Notebook {
+    let lines = markdown.split_inclusive('\n').collect::<Vec<_>>();
+    let mut notebook = Notebook {
+        markdown: String::new(),
+        cells: vec![],
+    };
+
+    let mut current = 0;
+    while current < lines.len() {
+        let line = lines[current];
+        notebook.markdown.push_str(line);
+        current += 1;
+
+        if line.trim_end() != CODE_FENCE {
+            continue;
+        }
+        // an unterminated block is left as is
+        let Some(end) = closing_fence(&lines, current) else {
+            continue;
+        };
+
+        let source = lines[current..end].concat();
+        notebook.markdown.push_str(&source);
+        notebook.markdown.push_str(lines[end]);
+        let mut cell = Cell {
+            line: current,
+            output: execute(interpreter, &source, current, lang),
+            expected: None,
+        };
+        current = end + 1;
+
+        // the output block may be separated from the code by one blank line
+        let start = match lines.get(current) {
+            Some(line) if line.trim().is_empty() => current + 1,
+            _ => current,
+        };
+        if lines.get(start).is_some_and(|line| line.trim_end() == OUTPUT_FENCE) {
+            if let Some(end) = closing_fence(&lines, start + 1) {
+                cell.expected = Some(lines[start + 1..end].concat());
+                current = end + 1;
+            }
+        }
+
+        if cell.expected.is_some() || !cell.output.is_empty() {
+            if !notebook.markdown.ends_with('\n') {
+                notebook.markdown.push('\n');
+            }
+            notebook
+                .markdown
+                .push_str(&format!("\n{}\n{}{}\n", OUTPUT_FENCE, cell.output, FENCE));
+        }
+        notebook.cells.push(cell);
+    }
+
+    notebook
+}
+
+fn closing_fence(lines: &[&str], from: usize) -> Option<usize> {
+    lines[from..]
+        .iter()
+        .position(|line| line.trim_end() == FENCE)
+        .map(|offset| from + offset)
+}
+
+/// Everything the block printed, then its value if it was a lone expression, then any diagnostics.
+/// `fence` is the line of the opening fence, so diagnostics point into the document.
+fn execute(interpreter: &mut Interpreter, source: &str, fence: usize, lang: Lang) -> String {
+    let outcome = run_with(interpreter, source);
+    let mut output = outcome.stdout;
+    if let Some(value) = outcome.value {
+        output.push_str(&format!("{}\n", interpreter.stringify(&value)));
+    }
+    for mut diagnostic in outcome.diagnostics {
+        diagnostic.line = diagnostic.line.map(|relative| fence + relative);
+        output.push_str(&format!("{}\n", diagnostic.render(lang)));
+    }
+    output
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn run(markdown: &str) -> Notebook {
+        super::run(&mut Interpreter::default(), markdown, Lang::En)
+    }
+
+    #[test]
+    fn test_run() {
+        let markdown =
+            "# Notes\n\n```lox\nvar a = 1;\nprint a;\n```\n\nText.\n\n```lox\na + 1\n```\n\n```rust\nlet a = 1;\n```\n";
+        let notebook = run(markdown);
+        assert_eq!(
+            notebook.markdown,
+            "# Notes\n\n```lox\nvar a = 1;\nprint a;\n```\n\n```output\n1\n```\n\nText.\n\n```lox\na + 1\n```\n\n```output\n2\n```\n\n```rust\nlet a = 1;\n```\n"
+        );
+        assert_eq!(notebook.cells.len(), 2);
+        assert_eq!(notebook.cells[1].line, 10);
+        assert!(notebook.cells.iter().all(|cell| !cell.is_up_to_date()));
+
+        // a second run only checks the outputs
+        let again = run(&notebook.markdown);
+        assert_eq!(again.markdown, notebook.markdown);
+        assert!(again.cells.iter().all(Cell::is_up_to_date));
+    }
+
+    #[test]
+    fn test_stale_output() {
+        let notebook = run("```lox\nprint 1 + 1;\n```\n```output\n3\n```\n```lox\nvar a;\n```\n");
+        assert_eq!(
+            notebook.markdown,
+            "```lox\nprint 1 + 1;\n```\n\n```output\n2\n```\n```lox\nvar a;\n```\n"
+        );
+        assert_eq!(notebook.cells[0].expected.as_deref(), Some("3\n"));
+        assert!(!notebook.cells[0].is_up_to_date());
+        assert!(notebook.cells[1].is_up_to_date());
+    }
+
+    #[test]
+    fn test_diagnostics() {
+        let notebook = run("Text.\n```lox\nprint -\"a\";\n```");
+        assert_eq!(
+            notebook.markdown,
+            "Text.\n```lox\nprint -\"a\";\n```\n\n```output\nOperand must be a number.\n[line 3]\n```\n"
+        );
+    }
+
+    #[test]
+    fn test_unterminated() {
+        let markdown = "```lox\nprint 1;\n";
+        let notebook = run(markdown);
+        assert_eq!(notebook.markdown, markdown);
+        assert!(notebook.cells.is_empty());
+    }
+}
@@ -0,0 +1,110 @@
+//! Standard output for the CLI, with configurable buffering and escaping.
+
+use lox::messages::Message;
+use std::{
+    cell::RefCell,
+    io::{self, BufWriter, Stdout, Write},
+    rc::Rc,
+    str::FromStr,
+};
+
+#[derive(Debug, Default, Clone, Copy, PartialEq)]
+pub enum Buffering {
+    /// Flush at every newline, the default for a terminal.
+    #[default]
+    Line,
+    /// Flush only when the buffer fills up and at exit, fastest when piping.
+    Full,
+    /// Flush after every write.
+    None,
+}
+
+impl FromStr for Buffering {
+    type Err = Message;
+
+    fn from_str(value: &str) -> Result<Self, Self::Err> {
+        match value {
+            "line" => Ok(Buffering::Line),
+            "full" => Ok(Buffering::Full),
+            "none" => Ok(Buffering::None),
+            _ => Err(Message::UnsupportedBuffering { value: value.into() }),
+        }
+    }
+}
+
+/// Clones write to the same buffer, so the interpreter's `print` output and the CLI's own stay in order.
+#[derive(Clone)]
+pub struct Output {
+    stdout: Rc<RefCell<BufWriter<Stdout>>>,
+    buffering: Buffering,
+    /// Escape everything but printable ASCII, for consumers that can't handle UTF-8.
+    ascii: bool,
+}
+
+impl Output {
+    pub fn new(buffering: Buffering, ascii: bool) -> Self {
+        Self {
+            stdout: Rc::new(RefCell::new(BufWriter::new(io::stdout()))),
+            buffering,
+            ascii,
+        }
+    }
+
+    pub fn write(&mut self, text: &str) -> io::Result<()> {
+        let mut stdout = self.stdout.borrow_mut();
+        if self.ascii {
+            write!(stdout, "{}", escape_ascii(text))?;
+        } else {
+            write!(stdout, "{}", text)?;
+        }
+
+        match self.buffering {
+            Buffering::Line if text.contains('\n') => stdout.flush(),
+            Buffering::None => stdout.flush(),
+            Buffering::Line | Buffering::Full => Ok(()),
+        }
+    }
+
+    pub fn write_line(&mut self, text: &str) -> io::Result<()> {
+        self.write(&format!("{}\n", text))
+    }
+
+    /// Flushes pending output, e.g. before writing to stderr so both streams interleave in order.
+    pub fn flush(&mut self) -> io::Result<()> {
+        self.stdout.borrow_mut().flush()
+    }
+}
+
+/// Lets the interpreter `print` straight to the CLI's output.
+impl Write for Output {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        Output::write(self, &String::from_utf8_lossy(buf))?;
+        Ok(buf.len())
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        Output::flush(self)
+    }
+}
+
+/// Replaces control and non-ASCII characters, except newlines and tabs, with `\u{…}` escapes.
+pub fn escape_ascii(text: &str) -> String {
+    text.chars()
+        .map(|c| match c {
+            '\n' | '\t' | ' '..='~' => c.to_string(),
+            c => format!("\\u{{{:x}}}", c as u32),
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_escape_ascii() {
+        assert_eq!(escape_ascii("plain \"text\"\n\tok"), "plain \"text\"\n\tok");
+        assert_eq!(escape_ascii("héllo\r"), "h\\u{e9}llo\\u{d}");
+        assert_eq!(escape_ascii("\u{1b}[31m"), "\\u{1b}[31m");
+    }
+}
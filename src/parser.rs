@@ -1,79 +1,329 @@
 use crate::{
-    ast::{binary, grouping, literal, unary, Expr},
+    ast::{assign, binary, grouping, literal, logical, unary, variable, Expr, Stmt},
+    messages::Message,
     token::{Literal, Token, TokenType},
 };
 
+/// How tightly a binary operator binds, from loosest to tightest.
+#[derive(Debug, Clone, Copy, PartialEq, PartialOrd)]
+enum Precedence {
+    Or,
+    And,
+    Equality,
+    Comparison,
+    Term,
+    Factor,
+    Unary,
+}
+
+impl Precedence {
+    fn next(self) -> Self {
+        match self {
+            Precedence::Or => Precedence::And,
+            Precedence::And => Precedence::Equality,
+            Precedence::Equality => Precedence::Comparison,
+            Precedence::Comparison => Precedence::Term,
+            Precedence::Term => Precedence::Factor,
+            Precedence::Factor | Precedence::Unary => Precedence::Unary,
+        }
+    }
+}
+
+/// Binary operators and their precedence. New operators only need an entry here.
+const BINARY_OPERATORS: &[(TokenType, Precedence)] = &[
+    (TokenType::Or, Precedence::Or),
+    (TokenType::And, Precedence::And),
+    (TokenType::BangEqual, Precedence::Equality),
+    (TokenType::EqualEqual, Precedence::Equality),
+    (TokenType::Greater, Precedence::Comparison),
+    (TokenType::GreaterEqual, Precedence::Comparison),
+    (TokenType::Less, Precedence::Comparison),
+    (TokenType::LessEqual, Precedence::Comparison),
+    (TokenType::Minus, Precedence::Term),
+    (TokenType::Plus, Precedence::Term),
+    (TokenType::Percent, Precedence::Factor),
+    (TokenType::Slash, Precedence::Factor),
+    (TokenType::Star, Precedence::Factor),
+];
+
+/// Compound assignment operators and the binary operator each applies: `a += b` is `a = a + b`.
+const COMPOUND_ASSIGNMENTS: &[(TokenType, TokenType)] = &[
+    (TokenType::MinusEqual, TokenType::Minus),
+    (TokenType::PercentEqual, TokenType::Percent),
+    (TokenType::PlusEqual, TokenType::Plus),
+    (TokenType::SlashEqual, TokenType::Slash),
+    (TokenType::StarEqual, TokenType::Star),
+];
+
+fn compound_operator(token_type: TokenType) -> Option<TokenType> {
+    COMPOUND_ASSIGNMENTS
+        .iter()
+        .find(|(compound, _)| *compound == token_type)
+        .map(|(_, operator)| *operator)
+}
+
+fn binary_precedence(token_type: TokenType) -> Option<Precedence> {
+    BINARY_OPERATORS
+        .iter()
+        .find(|(operator, _)| *operator == token_type)
+        .map(|(_, precedence)| *precedence)
+}
+
+/// Saved parser position, see [`Parser::checkpoint`].
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Checkpoint {
+    current: usize,
+    depth: usize,
+}
+
 #[derive(Debug)]
 pub enum ParserError {
-    ParseError(String),
+    /// The token the parser was looking at, or the one that was out of place. Boxed to keep `Result`s small,
+    /// see [`Parser::max_depth`].
+    ParseError(Box<Token>, Message),
+}
+
+/// Everything [`Parser::parse`] could make of the input: the statements that parsed,
+/// and the errors that made it skip the others.
+#[derive(Debug)]
+pub struct Program {
+    pub statements: Vec<Stmt>,
+    pub errors: Vec<ParserError>,
 }
 
+impl Program {
+    /// The statements, or the first error if there was one.
+    pub fn into_result(self) -> Result<Vec<Stmt>, ParserError> {
+        match self.errors.into_iter().next() {
+            Some(error) => Err(error),
+            None => Ok(self.statements),
+        }
+    }
+}
+
+/// Default for [`Parser::max_depth`].
+pub const MAX_DEPTH: usize = 256;
+
 pub struct Parser<'a> {
     pub tokens: &'a Vec<Token>,
     pub current: usize,
+    /// How deeply unary operators and groupings may nest before parsing is aborted,
+    /// so hostile input can't overflow the stack during recursive descent.
+    pub max_depth: usize,
+    depth: usize,
 }
 
 impl<'a> Parser<'a> {
     pub fn new(tokens: &'a Vec<Token>) -> Self {
-        Self { tokens, current: 0 }
+        Self {
+            tokens,
+            current: 0,
+            max_depth: MAX_DEPTH,
+            depth: 0,
+        }
     }
 
-    /// `equality` → `equality`
-    pub fn expression(&mut self) -> Result<Expr, ParserError> {
-        self.equality()
+    /// Saves the current position so a speculative parse can be undone with [`Parser::rewind`].
+    pub fn checkpoint(&self) -> Checkpoint {
+        Checkpoint {
+            current: self.current,
+            depth: self.depth,
+        }
+    }
+
+    /// Restores the state saved by [`Parser::checkpoint`], discarding everything consumed since.
+    pub fn rewind(&mut self, checkpoint: Checkpoint) {
+        self.current = checkpoint.current;
+        self.depth = checkpoint.depth;
     }
 
-    /// `equality` → `comparison ( ( "!=" | "==" ) comparison )*`
+    /// `program` → `declaration* EOF`
     ///
-    /// For each iteration, we create a new binary expression using the previous one as the left operand:  
-    /// `a == b == c == d == e`  ->  `(== (== (== (== a b) c) d) e)`
-    fn equality(&mut self) -> Result<Expr, ParserError> {
-        let mut left = self.comparison()?;
+    /// A declaration that fails to parse is recorded and skipped, so the rest of the input still
+    /// yields statements and further errors.
+    pub fn parse(&mut self) -> Program {
+        let mut program = Program {
+            statements: vec![],
+            errors: vec![],
+        };
+        while let Some(result) = self.next_statement() {
+            match result {
+                Ok(stmt) => program.statements.push(stmt),
+                Err(error) => program.errors.push(error),
+            }
+        }
+
+        program
+    }
 
-        while let Some(operator) = self.match_tokens(&[TokenType::BangEqual, TokenType::EqualEqual]) {
-            let right = self.comparison()?;
-            left = binary(left, operator, right);
+    /// Parses one declaration, `None` once the input is used up. After an error, parsing resumes at the
+    /// next statement, as in [`Parser::parse`].
+    pub fn next_statement(&mut self) -> Option<Result<Stmt, ParserError>> {
+        if self.is_at_end() {
+            return None;
         }
 
-        Ok(left)
+        let result = self.declaration();
+        if result.is_err() {
+            self.synchronize();
+        }
+        Some(result)
     }
 
-    /// `comparison` → `term ( ( ">" | ">=" | "<" | "<=" ) term )*`
-    fn comparison(&mut self) -> Result<Expr, ParserError> {
-        let mut left = self.term()?;
+    /// Discards tokens until the probable start of the next statement: just past a `;`,
+    /// or at a keyword that begins one.
+    fn synchronize(&mut self) {
+        self.advance();
 
-        while let Some(operator) = self.match_tokens(&[
-            TokenType::Greater,
-            TokenType::GreaterEqual,
-            TokenType::Less,
-            TokenType::LessEqual,
-        ]) {
-            let right = self.term()?;
-            left = binary(left, operator, right);
+        while !self.is_at_end() {
+            if self.tokens[self.current - 1].token_type == TokenType::Semicolon {
+                return;
+            }
+            match self.peek().token_type {
+                TokenType::Class
+                | TokenType::Fun
+                | TokenType::Var
+                | TokenType::For
+                | TokenType::If
+                | TokenType::While
+                | TokenType::Print
+                | TokenType::Return => return,
+                _ => {
+                    self.advance();
+                }
+            }
         }
+    }
 
-        Ok(left)
+    /// `declaration` → `varDecl | statement`
+    fn declaration(&mut self) -> Result<Stmt, ParserError> {
+        if self.match_token(TokenType::Var).is_some() {
+            return self.var_declaration();
+        }
+        self.statement()
     }
 
-    /// term → factor ( ( "-" | "+" ) factor )*
-    fn term(&mut self) -> Result<Expr, ParserError> {
-        let mut left = self.factor()?;
+    /// `varDecl` → `"var" IDENTIFIER ( "=" expression )? ";"`
+    fn var_declaration(&mut self) -> Result<Stmt, ParserError> {
+        let name = self.consume(TokenType::Identifier, Message::ExpectVariableName)?;
+
+        let initializer = match self.match_token(TokenType::Equal) {
+            Some(_) => Some(self.expression()?),
+            None => None,
+        };
 
-        while let Some(operator) = self.match_tokens(&[TokenType::Minus, TokenType::Plus]) {
-            let right = self.factor()?;
-            left = binary(left, operator, right);
+        self.consume(TokenType::Semicolon, Message::ExpectSemicolonAfterVariable)?;
+        Ok(Stmt::Var(name, initializer))
+    }
+
+    /// `statement` → `exprStmt | printStmt | breakStmt`
+    fn statement(&mut self) -> Result<Stmt, ParserError> {
+        if self.match_token(TokenType::Print).is_some() {
+            return self.print_statement();
+        }
+        // `breakStmt` → `"break" ";"`; there are no loops yet, so every `break` is outside of one.
+        if self.match_token(TokenType::Break).is_some() {
+            return Err(self.error_at(self.current - 1, Message::BreakOutsideLoop));
         }
+        self.expression_statement()
+    }
 
-        Ok(left)
+    /// `printStmt` → `"print" expression ";"`
+    fn print_statement(&mut self) -> Result<Stmt, ParserError> {
+        let value = self.expression()?;
+        self.consume(TokenType::Semicolon, Message::ExpectSemicolonAfterValue)?;
+        Ok(Stmt::Print(value))
+    }
+
+    /// `exprStmt` → `expression ";"`
+    fn expression_statement(&mut self) -> Result<Stmt, ParserError> {
+        let expr = self.expression()?;
+        self.consume(TokenType::Semicolon, Message::ExpectSemicolonAfterExpression)?;
+        Ok(Stmt::Expression(expr))
+    }
+
+    /// `expression` → `assignment`
+    pub fn expression(&mut self) -> Result<Expr, ParserError> {
+        self.assignment()
+    }
+
+    /// `assignment` → `IDENTIFIER ( "=" | "+=" | "-=" | "*=" | "/=" | "%=" ) assignment | binary_operation`
+    ///
+    /// The target is parsed as an ordinary expression first, since we only know it's an assignment
+    /// once we reach the `=`; it's then checked to be something assignable.
+    fn assignment(&mut self) -> Result<Expr, ParserError> {
+        let expr = self.binary_operation(Precedence::Or)?;
+
+        let equals = self.current;
+        if self.match_token(TokenType::Equal).is_some() {
+            let value = self.assigned_value()?;
+
+            return match expr {
+                Expr::Variable(name) => Ok(assign(name, value)),
+                _ => Err(self.error_at(equals, Message::InvalidAssignmentTarget)),
+            };
+        }
+        if let Some(operator) = compound_operator(self.peek().token_type) {
+            let compound = self.advance();
+            return self.compound_assignment(expr, compound, operator);
+        }
+
+        Ok(expr)
+    }
+
+    /// The right-hand side of an assignment. Assignment is right-associative, so `a = b = c` nests like
+    /// parentheses do and counts towards [`Parser::max_depth`].
+    fn assigned_value(&mut self) -> Result<Expr, ParserError> {
+        if self.depth >= self.max_depth {
+            return Err(self.error(Message::TooDeeplyNested));
+        }
+
+        self.depth += 1;
+        let value = self.assignment();
+        self.depth -= 1;
+
+        value
     }
 
-    /// factor → unary ( ( "/" | "*" ) unary )*
-    fn factor(&mut self) -> Result<Expr, ParserError> {
+    /// Desugars `target op= value` into `target = target op value`.
+    fn compound_assignment(&mut self, target: Expr, compound: Token, operator: TokenType) -> Result<Expr, ParserError> {
+        let value = self.assigned_value()?;
+
+        let Expr::Variable(name) = target else {
+            return Err(ParserError::ParseError(
+                Box::new(compound),
+                Message::InvalidAssignmentTarget,
+            ));
+        };
+        let operator = compound.prefix(operator, compound.lexeme().len() - 1);
+        Ok(assign(name.clone(), binary(variable(name), operator, value)))
+    }
+
+    /// `binary_operation` → `unary ( OPERATOR unary )*`, with operators taken from [`BINARY_OPERATORS`].
+    ///
+    /// Precedence climbing: an operator is only consumed here if it binds at least as tightly as `min`,
+    /// and its right operand only takes operators that bind tighter, so every level is left-associative:
+    /// `a == b == c == d == e`  ->  `(== (== (== (== a b) c) d) e)`
+    ///
+    /// Chains like `a < b < c` are rejected: they would compare the boolean `a < b` with `c`.
+    fn binary_operation(&mut self, min: Precedence) -> Result<Expr, ParserError> {
         let mut left = self.unary()?;
+        let mut previous: Option<Token> = None;
 
-        while let Some(operator) = self.match_tokens(&[TokenType::Slash, TokenType::Star]) {
-            let right = self.unary()?;
-            left = binary(left, operator, right);
+        while let Some(precedence) = binary_precedence(self.peek().token_type).filter(|precedence| *precedence >= min) {
+            let operator = self.advance();
+
+            if let Some(previous) = previous.filter(|_| precedence == Precedence::Comparison) {
+                return Err(self.chained_comparison(&previous, &operator));
+            }
+
+            let right = self.binary_operation(precedence.next())?;
+            let node = match precedence {
+                Precedence::Or | Precedence::And => logical,
+                _ => binary,
+            };
+            left = node(left, operator.clone(), right);
+            previous = (precedence == Precedence::Comparison).then_some(operator);
         }
 
         Ok(left)
@@ -81,6 +331,18 @@ impl<'a> Parser<'a> {
 
     /// unary → ( "!" | "-" ) unary | primary
     fn unary(&mut self) -> Result<Expr, ParserError> {
+        if self.depth >= self.max_depth {
+            return Err(self.error(Message::TooDeeplyNested));
+        }
+
+        self.depth += 1;
+        let expr = self.unary_inner();
+        self.depth -= 1;
+
+        expr
+    }
+
+    fn unary_inner(&mut self) -> Result<Expr, ParserError> {
         if let Some(operator) = self.match_tokens(&[TokenType::Bang, TokenType::Minus]) {
             let right = self.unary()?;
             return Ok(unary(operator, right));
@@ -88,42 +350,102 @@ impl<'a> Parser<'a> {
         self.primary()
     }
 
-    /// primary → NUMBER | STRING | "true" | "false" | "nil" | "(" expression ")"
+    /// primary → NUMBER | STRING | "true" | "false" | "nil" | "(" expression ")" | IDENTIFIER
+    ///        | "this"                      (error: outside of a class)
+    ///        | OPERATOR binary_operation   (error: missing left-hand operand)
     fn primary(&mut self) -> Result<Expr, ParserError> {
-        if let Some(token) = self.match_token(TokenType::Number) {
-            return Ok(literal(Literal::Number(token.lexeme.parse().unwrap())));
-        }
-        if let Some(token) = self.match_token(TokenType::String) {
-            return Ok(literal(token.literal.unwrap()));
+        if let Some(expr) = self.atom() {
+            return Ok(expr);
         }
-        if let Some(_) = self.match_token(TokenType::True) {
-            return Ok(literal(Literal::True));
-        }
-        if let Some(_) = self.match_token(TokenType::False) {
-            return Ok(literal(Literal::False));
+        if self.match_token(TokenType::LeftParen).is_some() {
+            let expr = self.expression()?;
+            self.consume(TokenType::RightParen, Message::ExpectRightParen)?;
+
+            return Ok(grouping(expr));
         }
-        if let Some(_) = self.match_token(TokenType::Nil) {
-            return Ok(literal(Literal::Nil));
+
+        // Error production: a binary operator with no left operand, e.g. `* 3`.
+        // The right operand is still consumed so parsing resumes after it.
+        if let Some(precedence) = binary_precedence(self.peek().token_type) {
+            let operator = self.current;
+            self.advance();
+            self.binary_operation(precedence.next())?;
+            return Err(self.missing_left_operand(operator));
         }
-        if let Some(_) = self.match_token(TokenType::LeftParen) {
-            let expr = self.expression()?;
 
-            // TODO: Lox error https://craftinginterpreters.com/parsing-expressions.html#entering-panic-mode
-            self.consume(TokenType::RightParen, "Expect ')' after expression.")
-                .unwrap();
+        Err(self.not_an_expression())
+    }
 
-            return Ok(grouping(expr));
+    // The error paths of `primary`, kept out of its stack frame like `atom`.
+
+    #[cold]
+    fn missing_left_operand(&self, operator: usize) -> ParserError {
+        let message = Message::MissingLeftOperand {
+            operator: self.tokens[operator].lexeme().into(),
+        };
+        self.error_at(operator, message)
+    }
+
+    #[cold]
+    fn not_an_expression(&mut self) -> ParserError {
+        // There are no class bodies yet, so every `this` is outside of one.
+        if self.match_token(TokenType::This).is_some() {
+            return self.error_at(self.current - 1, Message::ThisOutsideClass);
         }
+        self.error(Message::ExpectExpression)
+    }
 
-        Err(ParserError::ParseError("Expect expression.".into()))
+    /// The non-recursive part of `primary`: literals and identifiers.
+    ///
+    /// Kept out of `primary` so its temporaries aren't part of the stack frame repeated
+    /// at every nesting level, see [`Parser::max_depth`].
+    fn atom(&mut self) -> Option<Expr> {
+        if let Some(token) = self.match_token(TokenType::Number) {
+            return Some(literal(token.literal.unwrap()));
+        }
+        if let Some(token) = self.match_token(TokenType::String) {
+            return Some(literal(token.literal.unwrap()));
+        }
+        if self.match_token(TokenType::True).is_some() {
+            return Some(literal(Literal::True));
+        }
+        if self.match_token(TokenType::False).is_some() {
+            return Some(literal(Literal::False));
+        }
+        if self.match_token(TokenType::Nil).is_some() {
+            return Some(literal(Literal::Nil));
+        }
+        self.match_token(TokenType::Identifier).map(variable)
     }
 
-    fn consume(&mut self, token_type: TokenType, message: &str) -> Result<Token, ParserError> {
+    fn consume(&mut self, token_type: TokenType, message: Message) -> Result<Token, ParserError> {
         if self.check(token_type) {
             return Ok(self.advance());
         }
 
-        Err(ParserError::ParseError(message.into()))
+        Err(self.error(message))
+    }
+
+    #[cold]
+    fn chained_comparison(&self, first: &Token, second: &Token) -> ParserError {
+        let message = Message::ChainedComparison {
+            first: first.lexeme().into(),
+            second: second.lexeme().into(),
+        };
+        ParserError::ParseError(Box::new(second.clone()), message)
+    }
+
+    /// An error at the current token.
+    fn error(&self, message: Message) -> ParserError {
+        self.error_at(self.current, message)
+    }
+
+    /// An error at the token at `index`. Only the index is kept around on the recursive paths, so a cloned
+    /// token doesn't grow their stack frames, see [`Parser::max_depth`].
+    #[cold]
+    fn error_at(&self, index: usize, message: Message) -> ParserError {
+        let token = &self.tokens[index.min(self.tokens.len() - 1)];
+        ParserError::ParseError(Box::new(token.clone()), message)
     }
 
     fn match_tokens(&mut self, token_types: &[TokenType]) -> Option<Token> {
@@ -162,15 +484,20 @@ impl<'a> Parser<'a> {
         token.clone()
     }
 
-    fn is_at_end(&self) -> bool {
+    pub fn is_at_end(&self) -> bool {
         self.peek().token_type == TokenType::Eof
     }
 
-    fn peek(&self) -> Token {
-        self.tokens[self.current].clone()
+    fn peek(&self) -> &Token {
+        self.peek_nth(0)
     }
 
-    // TODO: https://craftinginterpreters.com/parsing-expressions.html#synchronizing-a-recursive-descent-parser
+    /// Looks `n` tokens past the current one without consuming anything; `peek_nth(0)` is the current token.
+    /// Looking past the end yields the trailing `Eof`.
+    pub fn peek_nth(&self, n: usize) -> &Token {
+        let index = (self.current + n).min(self.tokens.len() - 1);
+        &self.tokens[index]
+    }
 }
 
 #[cfg(test)]
@@ -188,10 +515,22 @@ mod tests {
             ("1 - 2", "(- 1 2)"),
             ("1 * 2", "(* 1 2)"),
             ("1 / 2", "(/ 1 2)"),
+            ("1 + 7 % 3 * 2", "(+ 1 (* (% 7 3) 2))"),
+            ("1e3 - 2.5e-3", "(- 1000 0.0025)"),
+            ("a += 1", "(= a (+ a 1))"),
+            ("a *= b -= 2 + 3", "(= a (* a (= b (- b (+ 2 3)))))"),
+            ("a %= b or c", "(= a (% a (or b c)))"),
             ("1 + 2 * 3", "(+ 1 (* 2 3))"),
             ("1 + 2 * 3 - 4", "(- (+ 1 (* 2 3)) 4)"),
             ("1 + (2 * 3) - 4", "(- (+ 1 (group (* 2 3))) 4)"),
             ("1 + (2 * 3) - (4 * 5)", "(- (+ 1 (group (* 2 3))) (group (* 4 5)))"),
+            ("1 == 2 == 3", "(== (== 1 2) 3)"),
+            ("1 < 2 == 3 > 4", "(== (< 1 2) (> 3 4))"),
+            ("-1 * -2 + 3 < 4", "(< (+ (* (- 1) (- 2)) 3) 4)"),
+            ("a or b and c", "(or a (and b c))"),
+            ("a and b or c and d", "(or (and a b) (and c d))"),
+            ("1 < 2 and 2 < 3 == true", "(and (< 1 2) (== (< 2 3) true))"),
+            ("a = b or c", "(= a (or b c))"),
         ];
 
         for (input, expected) in tests {
@@ -202,4 +541,209 @@ mod tests {
             assert_eq!(expr.to_string(), expected);
         }
     }
+
+    #[test]
+    fn test_declarations() {
+        let mut scanner = Scanner::new("var a; var b = 1 + 2; a = b = 3;".into());
+        let tokens = scanner.scan_tokens();
+        let mut parser = Parser::new(tokens);
+        let statements = parser.parse().into_result().unwrap();
+        assert_eq!(statements.len(), 3);
+        assert!(matches!(&statements[0], Stmt::Var(name, None) if name.lexeme() == "a"));
+        assert!(
+            matches!(&statements[1], Stmt::Var(name, Some(expr)) if name.lexeme() == "b" && expr.to_string() == "(+ 1 2)")
+        );
+        assert!(matches!(&statements[2], Stmt::Expression(expr) if expr.to_string() == "(= a (= b 3))"));
+    }
+
+    #[test]
+    fn test_print_statement() {
+        let mut scanner = Scanner::new("print 1 + 2;".into());
+        let tokens = scanner.scan_tokens();
+        let mut parser = Parser::new(tokens);
+        let statements = parser.parse().into_result().unwrap();
+        assert!(matches!(&statements[..], [Stmt::Print(expr)] if expr.to_string() == "(+ 1 2)"));
+    }
+
+    #[test]
+    fn test_declaration_errors() {
+        let tests = [
+            ("var 1 = 2;", Message::ExpectVariableName),
+            ("var a = 1", Message::ExpectSemicolonAfterVariable),
+            ("a + 1", Message::ExpectSemicolonAfterExpression),
+            ("1 + a = 2;", Message::InvalidAssignmentTarget),
+            ("(a) = 2;", Message::InvalidAssignmentTarget),
+            ("1 += 2;", Message::InvalidAssignmentTarget),
+            ("print this;", Message::ThisOutsideClass),
+            ("break;", Message::BreakOutsideLoop),
+            ("var a = 1 + this.b;", Message::ThisOutsideClass),
+        ];
+
+        for (input, expected) in tests {
+            let mut scanner = Scanner::new(input.into());
+            let tokens = scanner.scan_tokens();
+            let mut parser = Parser::new(tokens);
+            let Err(ParserError::ParseError(_, message)) = parser.parse().into_result() else {
+                panic!("expected a parse error for {}", input);
+            };
+            assert_eq!(message, expected, "{}", input);
+        }
+    }
+
+    #[test]
+    fn test_error_recovery() {
+        let mut scanner = Scanner::new("var 1 = 2; print 1; a + ; (1; print 2; var b = 3".into());
+        let tokens = scanner.scan_tokens();
+        let program = Parser::new(tokens).parse();
+
+        assert_eq!(program.statements.len(), 2);
+        assert!(matches!(&program.statements[0], Stmt::Print(expr) if expr.to_string() == "1"));
+        assert!(matches!(&program.statements[1], Stmt::Print(expr) if expr.to_string() == "2"));
+
+        let errors = program
+            .errors
+            .into_iter()
+            .map(|ParserError::ParseError(_, message)| message)
+            .collect::<Vec<_>>();
+        assert_eq!(
+            errors,
+            [
+                Message::ExpectVariableName,
+                Message::ExpectExpression,
+                Message::ExpectRightParen,
+                Message::ExpectSemicolonAfterVariable,
+            ]
+        );
+    }
+
+    #[test]
+    fn test_chained_comparison() {
+        let mut scanner = Scanner::new("1 < 2 <= 3".into());
+        let tokens = scanner.scan_tokens();
+        let mut parser = Parser::new(tokens);
+        let Err(ParserError::ParseError(_, message)) = parser.expression() else {
+            panic!("expected a parse error");
+        };
+        assert_eq!(
+            message.to_string(),
+            "Comparisons can't be chained: 'a < b <= c' compares the result of 'a < b' with 'c'."
+        );
+    }
+
+    #[test]
+    fn test_missing_left_operand() {
+        for (input, operator) in [
+            ("* 3", "*"),
+            ("== 4", "=="),
+            ("1 + (<= 2 + 3)", "<="),
+            ("/ (1 + 2)", "/"),
+        ] {
+            let mut scanner = Scanner::new(input.into());
+            let tokens = scanner.scan_tokens();
+            let mut parser = Parser::new(tokens);
+            let Err(ParserError::ParseError(_, message)) = parser.expression() else {
+                panic!("expected a parse error for {}", input);
+            };
+            assert_eq!(
+                message.to_string(),
+                format!("Binary operator '{}' requires a left-hand operand.", operator)
+            );
+        }
+
+        // the right operand is consumed
+        let mut scanner = Scanner::new("== 3 + 4) * 2".into());
+        let tokens = scanner.scan_tokens();
+        let mut parser = Parser::new(tokens);
+        assert!(parser.expression().is_err());
+        assert_eq!(parser.peek().token_type, TokenType::RightParen);
+    }
+
+    #[test]
+    fn test_nesting_limit() {
+        let input = "(".repeat(2_000) + "1" + &")".repeat(2_000);
+        let mut scanner = Scanner::new(input);
+        let tokens = scanner.scan_tokens();
+        let mut parser = Parser::new(tokens);
+        let Err(ParserError::ParseError(_, message)) = parser.expression() else {
+            panic!("expected a parse error");
+        };
+        assert_eq!(message, Message::TooDeeplyNested);
+
+        let mut scanner = Scanner::new("--(-(1))".into());
+        let tokens = scanner.scan_tokens();
+        let mut parser = Parser::new(tokens);
+        parser.max_depth = 5;
+        assert!(parser.expression().is_err());
+        let mut parser = Parser::new(tokens);
+        parser.max_depth = 6;
+        assert!(parser.expression().is_ok());
+    }
+
+    #[test]
+    fn test_assignment_nesting_limit() {
+        for operator in ["=", "+="] {
+            let input = format!("a {} ", operator).repeat(100_000) + "1";
+            let mut scanner = Scanner::new(input);
+            let tokens = scanner.scan_tokens();
+            let Err(ParserError::ParseError(_, message)) = Parser::new(tokens).expression() else {
+                panic!("expected a parse error for {}", operator);
+            };
+            assert_eq!(message, Message::TooDeeplyNested);
+        }
+
+        let mut scanner = Scanner::new("a = b += c = 1".into());
+        let tokens = scanner.scan_tokens();
+        assert!(Parser::new(tokens).expression().is_ok());
+    }
+
+    #[test]
+    fn test_peek_nth() {
+        let mut scanner = Scanner::new("(a, b)".into());
+        let tokens = scanner.scan_tokens();
+        let mut parser = Parser::new(tokens);
+        assert_eq!(parser.peek_nth(0).token_type, TokenType::LeftParen);
+        assert_eq!(parser.peek_nth(2).token_type, TokenType::Comma);
+
+        parser.advance();
+        assert_eq!(parser.peek_nth(1).token_type, TokenType::Comma);
+        assert_eq!(parser.peek_nth(3).token_type, TokenType::RightParen);
+        assert_eq!(parser.peek_nth(4).token_type, TokenType::Eof);
+        assert_eq!(parser.peek_nth(100).token_type, TokenType::Eof);
+        assert_eq!(parser.current, 1);
+    }
+
+    #[test]
+    fn test_checkpoint_rewind() {
+        let mut scanner = Scanner::new("(1 + ) 2 * 3".into());
+        let tokens = scanner.scan_tokens();
+        let mut parser = Parser::new(tokens);
+
+        let checkpoint = parser.checkpoint();
+        assert!(parser.expression().is_err());
+        assert_ne!(parser.checkpoint(), checkpoint);
+
+        parser.rewind(checkpoint);
+        assert_eq!(parser.checkpoint(), checkpoint);
+        assert_eq!(parser.advance().token_type, TokenType::LeftParen);
+        assert_eq!(parser.advance().token_type, TokenType::Number);
+        parser.advance();
+        parser.advance();
+        assert_eq!(parser.expression().unwrap().to_string(), "(* 2 3)");
+    }
+
+    #[test]
+    fn test_rewind_restores_depth() {
+        let input = "(".repeat(10) + "1";
+        let mut scanner = Scanner::new(input);
+        let tokens = scanner.scan_tokens();
+        let mut parser = Parser::new(tokens);
+        parser.max_depth = 12;
+
+        for _ in 0..3 {
+            let checkpoint = parser.checkpoint();
+            assert!(parser.expression().is_err());
+            parser.rewind(checkpoint);
+        }
+        assert_eq!(parser.depth, 0);
+    }
 }
@@ -0,0 +1,103 @@
+use std::{
+    fs, io,
+    path::{Path, PathBuf},
+};
+
+const MAIN: &str = r#"print "Hello, world!";
+"#;
+
+const EXAMPLE_TEST: &str = r#"// Each `expect:` comment is the output the line above it should print.
+print 1 + 2 * 3;
+// expect: 7
+"#;
+
+/// Creates a starter project in `path`: a `lox.toml` manifest, `main.lox` and an example test in `tests/`.
+///
+/// Existing files are never overwritten. Returns the created files.
+pub fn init(path: &Path) -> io::Result<Vec<PathBuf>> {
+    let name = fs::canonicalize(path)
+        .ok()
+        .or_else(|| Some(path.to_path_buf()))
+        .and_then(|path| path.file_name().map(|name| name.to_string_lossy().into_owned()))
+        .unwrap_or_else(|| "lox-project".into());
+
+    let manifest = format!(
+        "[package]\nname = \"{}\"\nversion = \"0.1.0\"\nmain = \"main.lox\"\n",
+        toml_string(&name)
+    );
+    let files = [
+        (path.join("lox.toml"), manifest.as_str()),
+        (path.join("main.lox"), MAIN),
+        (path.join("tests").join("arithmetic.lox"), EXAMPLE_TEST),
+    ];
+
+    if let Some((existing, _)) = files.iter().find(|(file, _)| file.exists()) {
+        return Err(io::Error::new(
+            io::ErrorKind::AlreadyExists,
+            format!("{} already exists", existing.display()),
+        ));
+    }
+
+    fs::create_dir_all(path.join("tests"))?;
+    for (file, contents) in &files {
+        fs::write(file, contents)?;
+    }
+
+    Ok(files.into_iter().map(|(file, _)| file).collect())
+}
+
+/// Escapes `value` for a TOML basic string: quotes, backslashes and control characters.
+fn toml_string(value: &str) -> String {
+    value
+        .chars()
+        .map(|c| match c {
+            '"' => "\\\"".into(),
+            '\\' => "\\\\".into(),
+            c if c.is_control() => format!("\\u{:04X}", c as u32),
+            c => c.to_string(),
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_init() {
+        let dir = std::env::temp_dir().join(format!("lox-init-{}", std::process::id()));
+        let _ = fs::remove_dir_all(&dir);
+
+        let created = init(&dir).unwrap();
+        assert_eq!(created.len(), 3);
+        assert!(dir.join("tests/arithmetic.lox").is_file());
+
+        let manifest = fs::read_to_string(dir.join("lox.toml")).unwrap();
+        assert!(manifest.contains(&format!("name = \"lox-init-{}\"", std::process::id())));
+
+        let main = fs::read_to_string(dir.join("main.lox")).unwrap();
+        assert_eq!(crate::run(&main).stdout, "Hello, world!\n");
+        let example = fs::read_to_string(dir.join("tests/arithmetic.lox")).unwrap();
+        assert_eq!(crate::run(&example).stdout, "7\n");
+
+        // refuses to overwrite
+        let err = init(&dir).unwrap_err();
+        assert_eq!(err.kind(), io::ErrorKind::AlreadyExists);
+
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn test_manifest_name_is_escaped() {
+        let dir = std::env::temp_dir().join(format!("lox \"init\" \\{}", std::process::id()));
+        let _ = fs::remove_dir_all(&dir);
+
+        init(&dir).unwrap();
+        let manifest = fs::read_to_string(dir.join("lox.toml")).unwrap();
+        let expected = format!("name = \"lox \\\"init\\\" \\\\{}\"\n", std::process::id());
+        assert!(manifest.contains(&expected), "{}", manifest);
+
+        fs::remove_dir_all(&dir).unwrap();
+        assert_eq!(toml_string("tab\there"), "tab\\u0009here");
+    }
+}
@@ -1,21 +1,41 @@
-use crate::token::*;
+use std::rc::Rc;
+
+use crate::{dialect::Dialect, messages::Message, token::*};
+
+/// A problem in the source text, with the line it's on.
+#[derive(Debug, Clone, PartialEq)]
+pub struct ScanError(pub usize, pub Message);
 
 pub struct Scanner {
-    source: String,
+    source: Rc<str>,
     tokens: Vec<Token>,
+    errors: Vec<ScanError>,
     start: usize,
     current: usize,
     line: usize,
+    dialect: Dialect,
+    /// A newline was seen since the last token.
+    newline: bool,
+    /// The source ended inside a string.
+    open_string: bool,
 }
 
 impl Scanner {
     pub fn new(source: String) -> Self {
+        Self::with_dialect(source, Dialect::default())
+    }
+
+    pub fn with_dialect(source: String, dialect: Dialect) -> Self {
         Self {
-            source,
+            source: source.into(),
             start: 0,
             current: 0,
             line: 1,
             tokens: vec![],
+            errors: vec![],
+            dialect,
+            newline: false,
+            open_string: false,
         }
     }
 
@@ -25,11 +45,39 @@ impl Scanner {
             self.scan_token();
         }
 
-        self.tokens.push(Token::new(TokenType::Eof, "".into(), None, self.line));
+        self.start = self.current;
+        if self.dialect.newline_terminators && self.ends_statement() {
+            self.tokens.push(self.empty_token(TokenType::Semicolon, self.line));
+        }
+        self.tokens.push(self.empty_token(TokenType::Eof, self.line));
+
+        &self.tokens
+    }
 
+    /// Tokens from the last [`Scanner::scan_tokens`].
+    pub fn tokens(&self) -> &Vec<Token> {
         &self.tokens
     }
 
+    /// Errors found by [`Scanner::scan_tokens`]; scanning carries on past them.
+    pub fn errors(&self) -> &[ScanError] {
+        &self.errors
+    }
+
+    /// Whether the scanned source stops partway through a statement: inside a string or brackets, or
+    /// right after a token that needs something to follow it. A REPL reads another line instead of running it.
+    pub fn is_incomplete(&self) -> bool {
+        let depth = self.tokens.iter().fold(0, |depth: i32, token| match token.token_type {
+            TokenType::LeftParen | TokenType::LeftBrace => depth + 1,
+            TokenType::RightParen | TokenType::RightBrace => depth - 1,
+            _ => depth,
+        });
+        // `Eof` and implicit semicolons have no lexeme
+        let last = self.tokens.iter().rev().find(|token| !token.lexeme().is_empty());
+
+        self.open_string || depth > 0 || last.is_some_and(|token| expects_more(token.token_type))
+    }
+
     fn scan_token(&mut self) {
         let c = self.advance();
         match c {
@@ -39,10 +87,23 @@ impl Scanner {
             '}' => self.add_token(TokenType::RightBrace, None),
             ',' => self.add_token(TokenType::Comma, None),
             '.' => self.add_token(TokenType::Dot, None),
-            '-' => self.add_token(TokenType::Minus, None),
-            '+' => self.add_token(TokenType::Plus, None),
+            '-' => match self.match_second('=') {
+                true => self.add_token(TokenType::MinusEqual, None),
+                false => self.add_token(TokenType::Minus, None),
+            },
+            '+' => match self.match_second('=') {
+                true => self.add_token(TokenType::PlusEqual, None),
+                false => self.add_token(TokenType::Plus, None),
+            },
+            '%' => match self.match_second('=') {
+                true => self.add_token(TokenType::PercentEqual, None),
+                false => self.add_token(TokenType::Percent, None),
+            },
             ';' => self.add_token(TokenType::Semicolon, None),
-            '*' => self.add_token(TokenType::Star, None),
+            '*' => match self.match_second('=') {
+                true => self.add_token(TokenType::StarEqual, None),
+                false => self.add_token(TokenType::Star, None),
+            },
             '!' => match self.match_second('=') {
                 true => self.add_token(TokenType::BangEqual, None),
                 false => self.add_token(TokenType::Bang, None),
@@ -57,6 +118,7 @@ impl Scanner {
                 true => self.add_token(TokenType::GreaterEqual, None),
                 false => self.add_token(TokenType::Greater, None),
             },
+            '/' if self.match_second('=') => self.add_token(TokenType::SlashEqual, None),
             '/' => match self.match_second('/') {
                 false => self.add_token(TokenType::Slash, None),
                 true => {
@@ -66,10 +128,13 @@ impl Scanner {
                 }
             },
             '"' => self.string(),
-            token if token.is_digit(10) => self.number(),
+            token if token.is_ascii_digit() => self.number(),
             token if token.is_alphabetic() => self.identifier(),
             ' ' | '\t' | '\r' => (),
-            '\n' => self.line += 1,
+            '\n' => {
+                self.line += 1;
+                self.newline = true;
+            }
             _ => {
                 // TODO: Lox.error(line, "Unexpected character.");
             }
@@ -77,65 +142,189 @@ impl Scanner {
     }
 
     fn add_token(&mut self, token_type: TokenType, literal: Option<Literal>) {
-        let text = self.source[self.start..self.current].to_string();
-        self.tokens.push(Token::new(token_type, text, literal, self.line));
+        if self.newline && self.dialect.newline_terminators && self.ends_statement() && !continues(token_type) {
+            let line = self.tokens.last().map_or(self.line, |token| token.line);
+            self.tokens.push(self.empty_token(TokenType::Semicolon, line));
+        }
+        self.newline = false;
+
+        let span = Span {
+            start: self.start,
+            end: self.current,
+        };
+        self.tokens.push(Token::spanned(
+            token_type,
+            self.source.clone(),
+            span,
+            literal,
+            self.line,
+        ));
     }
 
-    fn identifier(&mut self) {
-        while self.peek().is_alphanumeric() {
-            self.advance();
-        }
+    /// A token without a lexeme, at the start of the current one.
+    fn empty_token(&self, token_type: TokenType, line: usize) -> Token {
+        let span = Span {
+            start: self.start,
+            end: self.start,
+        };
+        Token::spanned(token_type, self.source.clone(), span, None, line)
+    }
 
-        let text = &self.source[self.start..self.current];
+    /// Whether the last scanned token can be the final token of a statement.
+    fn ends_statement(&self) -> bool {
+        matches!(
+            self.tokens.last().map(|token| token.token_type),
+            Some(
+                TokenType::Identifier
+                    | TokenType::String
+                    | TokenType::Number
+                    | TokenType::True
+                    | TokenType::False
+                    | TokenType::Nil
+                    | TokenType::This
+                    | TokenType::Return
+                    | TokenType::Break
+                    | TokenType::RightParen
+            )
+        )
+    }
 
-        let token_type: TokenType = text.try_into().unwrap_or(TokenType::Identifier);
+    fn identifier(&mut self) {
+        let rest = self.source[self.current..].chars();
+        self.current += rest
+            .take_while(|c| c.is_alphanumeric())
+            .map(char::len_utf8)
+            .sum::<usize>();
+
+        let text = &self.source.as_bytes()[self.start..self.current];
+        let token_type = keyword(text).unwrap_or(TokenType::Identifier);
 
         self.add_token(token_type, None);
     }
 
     fn number(&mut self) {
-        while self.peek().is_digit(10) {
-            self.advance();
+        if self.source[self.start..].starts_with('0') {
+            if let Some(radix) = radix(self.peek()) {
+                self.advance();
+                return self.radix_number(radix);
+            }
         }
 
-        if self.peek() == '.' && self.peek_next().is_digit(10) {
+        self.digits();
+
+        if self.peek() == '.' && self.peek_next().is_ascii_digit() {
             // consume the dot
             self.advance();
+            self.digits();
+        }
 
-            while self.peek().is_digit(10) {
-                self.advance();
+        // exponent: `1e9`, `2.5e-3`, `1E+6`
+        if matches!(self.peek(), 'e' | 'E') {
+            let sign = matches!(self.peek_next(), '+' | '-') as usize;
+            let digit = self.source[self.current..].chars().nth(1 + sign);
+            if digit.is_some_and(|c| c.is_ascii_digit()) {
+                // consume the `e` and sign
+                for _ in 0..=sign {
+                    self.advance();
+                }
+                self.digits();
             }
         }
 
-        let value: f64 = self.source[self.start..self.current].parse().unwrap();
+        let literal = &self.source[self.start..self.current];
+        let value = match separators_valid(literal, 10) {
+            true => literal.replace('_', "").parse().unwrap(),
+            false => self.invalid_number(),
+        };
+
+        self.add_token(TokenType::Number, Some(Literal::Number(value)));
+    }
+
+    /// `0x1F`, `0b1010` or `0o755`, with the prefix already consumed.
+    fn radix_number(&mut self, radix: u32) {
+        // anything alphanumeric belongs to the literal, so `0x1G` is one bad number rather than `0x1` and `G`
+        while self.peek().is_alphanumeric() || self.peek() == '_' {
+            self.advance();
+        }
+
+        let literal = &self.source[self.start..self.current];
+        let digits = literal[2..].replace('_', "");
+        let value = match digits.is_empty() || !separators_valid(literal, radix) {
+            true => None,
+            false => digits.chars().try_fold(0.0, |value, c| {
+                c.to_digit(radix).map(|digit| value * radix as f64 + digit as f64)
+            }),
+        };
+        let value = value.unwrap_or_else(|| self.invalid_number());
 
         self.add_token(TokenType::Number, Some(Literal::Number(value)));
     }
 
+    /// Consumes a run of digits, which may be grouped with `_`.
+    fn digits(&mut self) {
+        while self.peek().is_ascii_digit() || self.peek() == '_' {
+            self.advance();
+        }
+    }
+
+    fn invalid_number(&mut self) -> f64 {
+        let literal = self.source[self.start..self.current].to_string();
+        self.errors
+            .push(ScanError(self.line, Message::InvalidNumber { literal }));
+        0.0
+    }
+
     fn string(&mut self) {
+        let mut value = String::new();
         while self.peek() != '"' && !self.is_at_end() {
-            if self.peek() == '\n' {
-                self.line += 1;
+            let c = self.advance();
+            match c {
+                '\n' => {
+                    self.line += 1;
+                    value.push(c);
+                }
+                '\\' if !self.is_at_end() => self.escape(&mut value),
+                _ => value.push(c),
             }
-            self.advance();
         }
 
         if self.is_at_end() {
             // TODO: Lox.error(line, "Unterminated string.");
+            self.open_string = true;
             return;
         }
 
         // closing quote
         self.advance();
 
-        let value = self.source[self.start + 1..self.current - 1].to_string();
-        println!("value: {}", value);
-        self.add_token(TokenType::String, Some(Literal::String(value)));
+        self.add_token(TokenType::String, Some(Literal::String(value.into())));
+    }
+
+    /// Appends the character escaped by the backslash just consumed.
+    fn escape(&mut self, value: &mut String) {
+        let c = self.advance();
+        let escaped = match c {
+            'n' => '\n',
+            't' => '\t',
+            'r' => '\r',
+            '0' => '\0',
+            '\\' | '"' => c,
+            _ => {
+                let escape = format!("\\{}", c);
+                self.errors
+                    .push(ScanError(self.line, Message::UnknownEscape { escape }));
+                if c == '\n' {
+                    self.line += 1;
+                }
+                return;
+            }
+        };
+        value.push(escaped);
     }
 
     fn advance(&mut self) -> char {
-        let char = self.source.chars().nth(self.current).unwrap();
-        self.current += 1;
+        let char = self.peek();
+        self.current += char.len_utf8();
         char
     }
 
@@ -143,11 +332,10 @@ impl Scanner {
         if self.is_at_end() {
             return false;
         }
-        let char = self.source.chars().nth(self.current).unwrap();
-        if char != expected {
+        if self.peek() != expected {
             return false;
         }
-        self.current += 1;
+        self.current += expected.len_utf8();
 
         true
     }
@@ -156,14 +344,11 @@ impl Scanner {
         if self.is_at_end() {
             return '\0';
         }
-        self.source.chars().nth(self.current).unwrap()
+        self.source[self.current..].chars().next().unwrap()
     }
 
     fn peek_next(&self) -> char {
-        if self.current + 1 >= self.source.len() {
-            return '\0';
-        }
-        self.source.chars().nth(self.current + 1).unwrap()
+        self.source[self.current..].chars().nth(1).unwrap_or('\0')
     }
 
     fn is_at_end(&self) -> bool {
@@ -171,6 +356,87 @@ impl Scanner {
     }
 }
 
+/// Whether a statement can't end with this token.
+fn expects_more(token_type: TokenType) -> bool {
+    matches!(
+        token_type,
+        TokenType::Comma
+            | TokenType::Dot
+            | TokenType::Minus
+            | TokenType::Percent
+            | TokenType::Plus
+            | TokenType::Slash
+            | TokenType::Star
+            | TokenType::Bang
+            | TokenType::BangEqual
+            | TokenType::Equal
+            | TokenType::EqualEqual
+            | TokenType::Greater
+            | TokenType::GreaterEqual
+            | TokenType::Less
+            | TokenType::LessEqual
+            | TokenType::MinusEqual
+            | TokenType::PercentEqual
+            | TokenType::PlusEqual
+            | TokenType::SlashEqual
+            | TokenType::StarEqual
+            | TokenType::And
+            | TokenType::Or
+            | TokenType::Print
+            | TokenType::Var
+    )
+}
+
+/// Radix of the number literal prefix `0<c>`.
+fn radix(c: char) -> Option<u32> {
+    match c {
+        'x' => Some(16),
+        'o' => Some(8),
+        'b' => Some(2),
+        _ => None,
+    }
+}
+
+/// Whether every `_` in a number literal sits between two digits, so `1_000` is fine but `1__0`, `1_` and
+/// `0x_FF` are not.
+fn separators_valid(literal: &str, radix: u32) -> bool {
+    let chars = literal.chars().collect::<Vec<_>>();
+    let is_digit = |index: Option<usize>| index.and_then(|i| chars.get(i)).is_some_and(|c| c.is_digit(radix));
+    (0..chars.len())
+        .filter(|&i| chars[i] == '_')
+        .all(|i| is_digit(i.checked_sub(1)) && is_digit(Some(i + 1)))
+}
+
+/// Whether a token at the start of a line continues the statement from the previous line.
+fn continues(token_type: TokenType) -> bool {
+    matches!(
+        token_type,
+        TokenType::RightParen
+            | TokenType::Comma
+            | TokenType::Dot
+            | TokenType::Minus
+            | TokenType::Percent
+            | TokenType::Plus
+            | TokenType::Semicolon
+            | TokenType::Slash
+            | TokenType::Star
+            | TokenType::BangEqual
+            | TokenType::Equal
+            | TokenType::EqualEqual
+            | TokenType::Greater
+            | TokenType::GreaterEqual
+            | TokenType::Less
+            | TokenType::LessEqual
+            | TokenType::MinusEqual
+            | TokenType::PercentEqual
+            | TokenType::PlusEqual
+            | TokenType::SlashEqual
+            | TokenType::StarEqual
+            | TokenType::And
+            | TokenType::Or
+    )
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -195,6 +461,28 @@ mod tests {
         assert_eq!(tokens[3].token_type, TokenType::Equal);
     }
 
+    #[test]
+    fn test_compound_assignment_tokens() {
+        let mut scanner = Scanner::new("+= -= *= /= %= / // comment".into());
+        let types = scanner
+            .scan_tokens()
+            .iter()
+            .map(|token| token.token_type)
+            .collect::<Vec<_>>();
+        assert_eq!(
+            types,
+            [
+                TokenType::PlusEqual,
+                TokenType::MinusEqual,
+                TokenType::StarEqual,
+                TokenType::SlashEqual,
+                TokenType::PercentEqual,
+                TokenType::Slash,
+                TokenType::Eof,
+            ]
+        );
+    }
+
     #[test]
     fn test_string_literal_tokens() {
         let mut scanner = Scanner::new("\"hello\"".into());
@@ -204,6 +492,29 @@ mod tests {
         assert_eq!(tokens[0].literal, Some(Literal::String("hello".into())));
     }
 
+    #[test]
+    fn test_string_escapes() {
+        let mut scanner = Scanner::new(r#""say \"hi\"\n\tand \\ \r\0" + 1"#.into());
+        let tokens = scanner.scan_tokens();
+        assert_eq!(tokens.len(), 4);
+        assert_eq!(
+            tokens[0].literal,
+            Some(Literal::String("say \"hi\"\n\tand \\ \r\0".into()))
+        );
+        assert!(scanner.errors().is_empty());
+    }
+
+    #[test]
+    fn test_unknown_escape() {
+        let mut scanner = Scanner::new("\"one\"\n\"two \\q\";".into());
+        let tokens = scanner.scan_tokens();
+        assert_eq!(tokens[1].literal, Some(Literal::String("two ".into())));
+        assert_eq!(
+            scanner.errors(),
+            [ScanError(2, Message::UnknownEscape { escape: "\\q".into() })]
+        );
+    }
+
     #[test]
     fn test_string_multiline_literal_tokens() {
         let mut scanner = Scanner::new("\"hello\nworld\"".into());
@@ -224,15 +535,156 @@ mod tests {
         assert_eq!(tokens[1].literal, Some(Literal::Number(42.0)));
     }
 
+    #[test]
+    fn test_exponent_number_tokens() {
+        let mut scanner = Scanner::new("1e9 2.5e-3 1E+6 7e0".into());
+        let values = scanner
+            .scan_tokens()
+            .iter()
+            .filter_map(|token| token.literal.clone())
+            .collect::<Vec<_>>();
+        assert_eq!(values, [1e9, 2.5e-3, 1e6, 7.0].map(Literal::Number));
+
+        // without digits the `e` isn't part of the number
+        let mut scanner = Scanner::new("1e 2e+".into());
+        let types = scanner
+            .scan_tokens()
+            .iter()
+            .map(|token| token.token_type)
+            .collect::<Vec<_>>();
+        assert_eq!(
+            types,
+            [
+                TokenType::Number,
+                TokenType::Identifier,
+                TokenType::Number,
+                TokenType::Identifier,
+                TokenType::Plus,
+                TokenType::Eof,
+            ]
+        );
+    }
+
+    #[test]
+    fn test_radix_number_tokens() {
+        let mut scanner = Scanner::new("0x1F 0xff 0b1010 0o755 0 0.5".into());
+        let values = scanner
+            .scan_tokens()
+            .iter()
+            .filter_map(|token| token.literal.clone())
+            .collect::<Vec<_>>();
+        assert_eq!(values, [31.0, 255.0, 10.0, 493.0, 0.0, 0.5].map(Literal::Number));
+        assert!(scanner.errors().is_empty());
+
+        for input in ["0x", "0b102", "0o8", "0x1G", "0xg"] {
+            let mut scanner = Scanner::new(input.into());
+            let tokens = scanner.scan_tokens();
+            assert_eq!(tokens.len(), 2, "{}", input);
+            assert_eq!(
+                scanner.errors(),
+                [ScanError(1, Message::InvalidNumber { literal: input.into() })]
+            );
+        }
+    }
+
+    #[test]
+    fn test_digit_separators() {
+        let mut scanner = Scanner::new("1_000_000 0xFF_FF 1_0.2_5e1_0 0b1_0".into());
+        let values = scanner
+            .scan_tokens()
+            .iter()
+            .filter_map(|token| token.literal.clone())
+            .collect::<Vec<_>>();
+        assert_eq!(values, [1e6, 65535.0, 10.25e10, 2.0].map(Literal::Number));
+        assert!(scanner.errors().is_empty());
+
+        for input in ["1_", "1__0", "1_.5", "1.5_", "1_e3", "0x_FF", "0xFF_", "0b1__0"] {
+            let mut scanner = Scanner::new(input.into());
+            let tokens = scanner.scan_tokens();
+            assert_eq!(tokens.len(), 2, "{}", input);
+            assert_eq!(
+                scanner.errors(),
+                [ScanError(1, Message::InvalidNumber { literal: input.into() })]
+            );
+        }
+    }
+
+    #[test]
+    fn test_spans() {
+        let mut scanner = Scanner::new("var é = \"ü\";".into());
+        let tokens = scanner
+            .scan_tokens()
+            .iter()
+            .map(|token| (token.lexeme(), token.span.start, token.span.end))
+            .collect::<Vec<_>>();
+        assert_eq!(
+            tokens,
+            [
+                ("var", 0, 3),
+                ("é", 4, 6),
+                ("=", 7, 8),
+                ("\"ü\"", 9, 13),
+                (";", 13, 14),
+                ("", 14, 14)
+            ]
+        );
+    }
+
+    #[test]
+    fn test_incomplete() {
+        let is_incomplete = |source: &str| {
+            let mut scanner = Scanner::new(source.into());
+            scanner.scan_tokens();
+            scanner.is_incomplete()
+        };
+
+        for source in [
+            "(1 +",
+            "(1 + 2",
+            "var a =",
+            "print",
+            "1 *",
+            "a or",
+            "\"two\nlines",
+            "{ (1) ",
+        ] {
+            assert!(is_incomplete(source), "{}", source);
+        }
+        for source in [
+            "", "1 + 2", "(1 + 2)", "print a;", "var a;", "\"(\"", "a) + (b", "// (", "-1",
+        ] {
+            assert!(!is_incomplete(source), "{}", source);
+        }
+    }
+
     #[test]
     fn test_identifier_tokens() {
         let mut scanner = Scanner::new("foo bar".into());
         let tokens = scanner.scan_tokens();
         assert_eq!(tokens.len(), 3);
         assert_eq!(tokens[0].token_type, TokenType::Identifier);
-        assert_eq!(tokens[0].lexeme, "foo");
+        assert_eq!(tokens[0].lexeme(), "foo");
         assert_eq!(tokens[1].token_type, TokenType::Identifier);
-        assert_eq!(tokens[1].lexeme, "bar");
+        assert_eq!(tokens[1].lexeme(), "bar");
+    }
+
+    #[test]
+    fn test_keyword_prefixes() {
+        let mut scanner = Scanner::new("an andy fo fork th thiss returns nill classy whilex i".into());
+        let tokens = scanner.scan_tokens();
+        assert!(tokens[..tokens.len() - 1]
+            .iter()
+            .all(|token| token.token_type == TokenType::Identifier));
+
+        for &keyword in KEYWORDS {
+            let mut scanner = Scanner::new(keyword.into());
+            assert_ne!(
+                scanner.scan_tokens()[0].token_type,
+                TokenType::Identifier,
+                "{}",
+                keyword
+            );
+        }
     }
 
     #[test]
@@ -251,4 +703,64 @@ mod tests {
         let tokens = scanner.scan_tokens();
         assert_eq!(tokens.len(), 3);
     }
+
+    #[test]
+    fn test_newline_terminators() {
+        let dialect = Dialect {
+            newline_terminators: true,
+            ..Default::default()
+        };
+        let types = |source: &str| {
+            let mut scanner = Scanner::with_dialect(source.into(), dialect);
+            scanner
+                .scan_tokens()
+                .iter()
+                .map(|token| token.token_type)
+                .collect::<Vec<_>>()
+        };
+
+        assert_eq!(
+            types("var a = 1\nprint a"),
+            [
+                TokenType::Var,
+                TokenType::Identifier,
+                TokenType::Equal,
+                TokenType::Number,
+                TokenType::Semicolon,
+                TokenType::Print,
+                TokenType::Identifier,
+                TokenType::Semicolon,
+                TokenType::Eof,
+            ]
+        );
+        // the next line continues the expression
+        assert_eq!(
+            types("1\n+ 2;"),
+            [
+                TokenType::Number,
+                TokenType::Plus,
+                TokenType::Number,
+                TokenType::Semicolon,
+                TokenType::Eof,
+            ]
+        );
+        // the line can't end a statement
+        assert_eq!(
+            types("1 +\n2"),
+            [
+                TokenType::Number,
+                TokenType::Plus,
+                TokenType::Number,
+                TokenType::Semicolon,
+                TokenType::Eof,
+            ]
+        );
+    }
+
+    #[test]
+    fn test_newline_terminators_disabled() {
+        let mut scanner = Scanner::new("1\n2".into());
+        let tokens = scanner.scan_tokens();
+        assert_eq!(tokens.len(), 3);
+    }
 }
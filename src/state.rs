@@ -0,0 +1,141 @@
+//! Saving and restoring an interpreter's globals, so a session can be persisted and resumed.
+//!
+//! The format is line based text, one global per entry and sorted by name:
+//!
+//! ```text
+//! lox-state 1
+//! number count 3
+//! string greeting 5
+//! hello
+//! bool done false
+//! nil pending
+//! ```
+//!
+//! A string's header holds its length in bytes, and the contents follow on their own line, so they may
+//! contain newlines.
+
+use std::io::{self, Read, Write};
+
+use crate::interpreter::{Environment, Interpreter, IntrResult};
+
+const HEADER: &str = "lox-state 1";
+
+impl Interpreter {
+    pub fn save_state(&self, mut writer: impl Write) -> io::Result<()> {
+        writeln!(writer, "{}", HEADER)?;
+
+        let mut globals = self.environment().iter().collect::<Vec<_>>();
+        globals.sort_by_key(|(name, _)| *name);
+        for (name, value) in globals {
+            match value {
+                IntrResult::Number(number) => writeln!(writer, "number {} {}", name, number)?,
+                IntrResult::String(string) => writeln!(writer, "string {} {}\n{}", name, string.len(), string)?,
+                IntrResult::Bool(value) => writeln!(writer, "bool {} {}", name, value)?,
+                IntrResult::None => writeln!(writer, "nil {}", name)?,
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Replaces all globals with the ones saved by [`Interpreter::save_state`].
+    /// On error the current globals are left untouched.
+    pub fn load_state(&mut self, mut reader: impl Read) -> io::Result<()> {
+        let mut state = String::new();
+        reader.read_to_string(&mut state)?;
+        let mut environment = Environment::default();
+
+        let mut rest = state
+            .strip_prefix(HEADER)
+            .and_then(|rest| rest.strip_prefix('\n'))
+            .ok_or_else(|| invalid("missing header"))?;
+        while !rest.is_empty() {
+            let (line, after) = rest.split_once('\n').ok_or_else(|| invalid("unterminated entry"))?;
+            rest = after;
+
+            let (name, value) = match line.split(' ').collect::<Vec<_>>()[..] {
+                ["number", name, number] => (name, IntrResult::Number(number.parse().map_err(|_| invalid(line))?)),
+                ["bool", name, value] => (name, IntrResult::Bool(value.parse().map_err(|_| invalid(line))?)),
+                ["nil", name] => (name, IntrResult::None),
+                ["string", name, len] => {
+                    let len: usize = len.parse().map_err(|_| invalid(line))?;
+                    let string = rest
+                        .get(..len)
+                        .filter(|_| rest[len..].starts_with('\n'))
+                        .ok_or_else(|| invalid(line))?;
+                    rest = &rest[len + 1..];
+                    (name, IntrResult::String(string.into()))
+                }
+                _ => return Err(invalid(line)),
+            };
+            environment.define(name, value);
+        }
+
+        *self.environment_mut() = environment;
+        Ok(())
+    }
+}
+
+fn invalid(reason: &str) -> io::Error {
+    io::Error::new(
+        io::ErrorKind::InvalidData,
+        format!("invalid interpreter state: {}", reason),
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{run_with, token::Token, token::TokenType};
+
+    use super::*;
+
+    fn global(interpreter: &Interpreter, name: &str) -> IntrResult {
+        let token = Token::new(TokenType::Identifier, name, None, 1);
+        interpreter.environment().get(&token).unwrap().clone()
+    }
+
+    #[test]
+    fn test_round_trip() {
+        let mut interpreter = Interpreter::default();
+        let source = "var count = 0.1 + 0.2; var text = \"two\nlines\"; var done = false; var pending;";
+        assert!(run_with(&mut interpreter, source).diagnostics.is_empty());
+
+        let mut saved = vec![];
+        interpreter.save_state(&mut saved).unwrap();
+        assert_eq!(
+            String::from_utf8(saved.clone()).unwrap(),
+            "lox-state 1\nnumber count 0.30000000000000004\nbool done false\nnil pending\nstring text 9\ntwo\nlines\n"
+        );
+
+        let mut restored = Interpreter::default();
+        run_with(&mut restored, "var stale = 1;");
+        restored.load_state(saved.as_slice()).unwrap();
+        assert!(!restored.environment().contains("stale"));
+        for name in ["count", "text", "done", "pending"] {
+            assert_eq!(global(&restored, name), global(&interpreter, name));
+        }
+
+        // the session carries on where it left off
+        let outcome = run_with(&mut restored, "text + \" and \" + \"more\"");
+        assert_eq!(outcome.value, Some(IntrResult::String("two\nlines and more".into())));
+    }
+
+    #[test]
+    fn test_invalid_state() {
+        let mut interpreter = Interpreter::default();
+        run_with(&mut interpreter, "var kept = 1;");
+
+        for state in [
+            "",
+            "lox-state 2\n",
+            "lox-state 1\nnumber a one\n",
+            "lox-state 1\nstring a 10\nshort\n",
+            "lox-state 1\nnil a",
+            "lox-state 1\nlist a\n",
+        ] {
+            let error = interpreter.load_state(state.as_bytes()).unwrap_err();
+            assert_eq!(error.kind(), io::ErrorKind::InvalidData, "{:?}", state);
+        }
+        assert!(interpreter.environment().contains("kept"));
+    }
+}
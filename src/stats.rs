@@ -0,0 +1,24 @@
+use std::{fmt::Display, time::Duration};
+
+/// Counters and per-phase timings collected during a single run.
+#[derive(Debug, Default, Clone, PartialEq)]
+pub struct ExecStats {
+    pub statements_executed: usize,
+    pub expressions_evaluated: usize,
+    /// Heap-allocated values created while evaluating (currently strings).
+    pub allocations: usize,
+    pub scan_time: Duration,
+    pub parse_time: Duration,
+    pub eval_time: Duration,
+}
+
+impl Display for ExecStats {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        writeln!(f, "statements executed:   {}", self.statements_executed)?;
+        writeln!(f, "expressions evaluated: {}", self.expressions_evaluated)?;
+        writeln!(f, "allocations:           {}", self.allocations)?;
+        writeln!(f, "scan:                  {:?}", self.scan_time)?;
+        writeln!(f, "parse:                 {:?}", self.parse_time)?;
+        write!(f, "eval:                  {:?}", self.eval_time)
+    }
+}
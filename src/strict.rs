@@ -0,0 +1,122 @@
+//! Strict mode: a static pass rejecting assignments to variables that were never declared,
+//! so a typo like `totl = total + 1;` is caught before any statement runs.
+
+use std::collections::HashSet;
+
+use crate::{
+    ast::{Expr, Stmt},
+    messages::Message,
+    token::{Literal, Token},
+};
+
+const DIRECTIVE: &str = "use strict";
+
+#[derive(Debug, PartialEq)]
+pub struct StrictError(pub Token, pub Message);
+
+/// Whether the program opts into strict mode with a leading `"use strict";`.
+pub fn has_directive(statements: &[Stmt]) -> bool {
+    matches!(
+        statements.first(),
+        Some(Stmt::Expression(Expr::Literal(Literal::String(directive)))) if &**directive == DIRECTIVE
+    )
+}
+
+/// Checks that every assignment targets a variable declared earlier in `statements`
+/// or already defined, according to `is_defined`.
+pub fn check(statements: &[Stmt], is_defined: impl Fn(&str) -> bool) -> Result<(), StrictError> {
+    let mut checker = Checker {
+        declared: HashSet::new(),
+        is_defined,
+    };
+
+    for stmt in statements {
+        match stmt {
+            Stmt::Expression(expr) | Stmt::Print(expr) => checker.expr(expr)?,
+            Stmt::Var(name, initializer) => {
+                if let Some(initializer) = initializer {
+                    checker.expr(initializer)?;
+                }
+                checker.declared.insert(name.lexeme().to_string());
+            }
+        }
+    }
+
+    Ok(())
+}
+
+/// Like [`check`], for a lone expression.
+pub fn check_expr(expr: &Expr, is_defined: impl Fn(&str) -> bool) -> Result<(), StrictError> {
+    Checker {
+        declared: HashSet::new(),
+        is_defined,
+    }
+    .expr(expr)
+}
+
+struct Checker<F> {
+    declared: HashSet<String>,
+    is_defined: F,
+}
+
+impl<F: Fn(&str) -> bool> Checker<F> {
+    fn expr(&self, expr: &Expr) -> Result<(), StrictError> {
+        match expr {
+            Expr::Assign(name, value) => {
+                self.expr(value)?;
+                if !self.declared.contains(name.lexeme()) && !(self.is_defined)(name.lexeme()) {
+                    return Err(StrictError(
+                        name.clone(),
+                        Message::UndeclaredAssignment {
+                            name: name.lexeme().to_string(),
+                        },
+                    ));
+                }
+                Ok(())
+            }
+            Expr::Binary(left, _, right) | Expr::Logical(left, _, right) => {
+                self.expr(left)?;
+                self.expr(right)
+            }
+            Expr::Grouping(expr) | Expr::Unary(_, expr) => self.expr(expr),
+            Expr::Literal(_) | Expr::Variable(_) => Ok(()),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{parser::Parser, scanner::Scanner};
+
+    use super::*;
+
+    fn parse(input: &str) -> Vec<Stmt> {
+        let mut scanner = Scanner::new(input.into());
+        let tokens = scanner.scan_tokens();
+        Parser::new(tokens).parse().into_result().unwrap()
+    }
+
+    #[test]
+    fn test_directive() {
+        assert!(has_directive(&parse("\"use strict\"; var a;")));
+        assert!(!has_directive(&parse("var a; \"use strict\";")));
+        assert!(!has_directive(&parse("\"use sloppy\";")));
+    }
+
+    #[test]
+    fn test_check() {
+        let none = |_: &str| false;
+        assert_eq!(check(&parse("var a; a = 1; var b = a = 2;"), none), Ok(()));
+        assert_eq!(check(&parse("a = 1;"), |name| name == "a"), Ok(()));
+
+        let Err(StrictError(token, message)) = check(&parse("var total = 0;\ntotl = total + 1;"), none) else {
+            panic!("expected a strict mode error");
+        };
+        assert_eq!(token.line, 2);
+        assert_eq!(message, Message::UndeclaredAssignment { name: "totl".into() });
+
+        // declared after use
+        assert!(check(&parse("1 + (a = 1); var a;"), none).is_err());
+        assert!(check(&parse("var a = a = 1;"), none).is_err());
+    }
+}
@@ -0,0 +1,92 @@
+//! Text templates evaluated by the interpreter.
+//!
+//! Text is copied as is, `{{ expr }}` is replaced by the value of `expr`, and `{% statements %}` runs the
+//! statements, keeping whatever they print. Everything shares one interpreter, so variables declared in
+//! a `{% %}` tag are visible to the tags after it.
+//!
+//! Rendering stops at the first tag that fails, with the diagnostic and whether it failed to compile or to run.
+
+use crate::{error::Diagnostic, interpreter::Interpreter, messages::Message, run_with, Failure};
+
+const TAGS: &[(&str, &str)] = &[("{{", "}}"), ("{%", "%}")];
+
+pub fn render(interpreter: &mut Interpreter, template: &str) -> Result<String, (Failure, Diagnostic)> {
+    let mut output = String::new();
+    let mut rest = template;
+    // line of the first character of `rest`
+    let mut line = 1;
+
+    while let Some((start, open, close)) = next_tag(rest) {
+        output.push_str(&rest[..start]);
+        line += rest[..start].matches('\n').count();
+
+        let after_open = &rest[start + open.len()..];
+        let Some(end) = after_open.find(close) else {
+            let diagnostic = Diagnostic {
+                message: Message::UnterminatedTag { tag: open.into() },
+                line: Some(line),
+            };
+            return Err((Failure::Compile, diagnostic));
+        };
+
+        let source = &after_open[..end];
+        let outcome = run_with(interpreter, source);
+        if let Some(mut diagnostic) = outcome.diagnostics.into_iter().next() {
+            // lines reported for `source` are relative to the tag
+            diagnostic.line = diagnostic.line.map(|relative| line + relative - 1);
+            return Err((outcome.failure.unwrap_or(Failure::Runtime), diagnostic));
+        }
+        output.push_str(&outcome.stdout);
+        if let Some(value) = outcome.value.filter(|_| open == "{{") {
+            output.push_str(&interpreter.stringify(&value));
+        }
+
+        let consumed = start + open.len() + end + close.len();
+        line += rest[start..consumed].matches('\n').count();
+        rest = &rest[consumed..];
+    }
+
+    output.push_str(rest);
+    Ok(output)
+}
+
+/// Position and delimiters of the first tag in `text`.
+fn next_tag(text: &str) -> Option<(usize, &'static str, &'static str)> {
+    TAGS.iter()
+        .filter_map(|(open, close)| text.find(open).map(|start| (start, *open, *close)))
+        .min_by_key(|(start, _, _)| *start)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn render(template: &str) -> Result<String, (Failure, Diagnostic)> {
+        super::render(&mut Interpreter::default(), template)
+    }
+
+    #[test]
+    fn test_render() {
+        let template =
+            "{% var name = \"Lox\"; var version = 1; %}Hello, {{ name }}!\nv{{ version + 1 }}{% print nil; %}";
+        assert_eq!(render(template), Ok("Hello, Lox!\nv2nil\n".into()));
+        assert_eq!(render("no tags {"), Ok("no tags {".into()));
+    }
+
+    #[test]
+    fn test_errors() {
+        let (failure, error) = render("line 1\n{{ 1 +\n\n true }}").unwrap_err();
+        assert_eq!(failure, Failure::Runtime);
+        assert_eq!(error.message, Message::OperandsMustBeNumbersOrStrings);
+        assert_eq!(error.line, Some(2));
+
+        let (failure, error) = render("{{ 1 + }}").unwrap_err();
+        assert_eq!(failure, Failure::Compile);
+        assert_eq!(error.message, Message::ExpectExpression);
+
+        let (failure, error) = render("{% var a; %}\nline 2 {{ a").unwrap_err();
+        assert_eq!(failure, Failure::Compile);
+        assert_eq!(error.message, Message::UnterminatedTag { tag: "{{".into() });
+        assert_eq!(error.line, Some(2));
+    }
+}
@@ -1,36 +1,88 @@
-#[derive(Debug, Clone, PartialEq)]
+use std::{fmt::Display, rc::Rc};
+
+/// Byte range of a token's lexeme in the source.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Span {
+    pub start: usize,
+    pub end: usize,
+}
+
+/// A token points into the source it was scanned from, which all tokens of a scan share, so making one
+/// doesn't allocate.
+#[derive(Clone)]
 pub struct Token {
     pub token_type: TokenType,
-    pub lexeme: String,
+    source: Rc<str>,
+    pub span: Span,
     pub literal: Option<Literal>,
     pub line: usize,
 }
 
 impl Token {
-    pub fn new(
-        token_type: TokenType,
-        lexeme: String,
-        literal: Option<Literal>,
-        line: usize,
-    ) -> Self {
+    /// A token with its own copy of `lexeme`, for tokens that weren't scanned.
+    pub fn new(token_type: TokenType, lexeme: &str, literal: Option<Literal>, line: usize) -> Self {
+        let span = Span {
+            start: 0,
+            end: lexeme.len(),
+        };
+        Self::spanned(token_type, lexeme.into(), span, literal, line)
+    }
+
+    pub fn spanned(token_type: TokenType, source: Rc<str>, span: Span, literal: Option<Literal>, line: usize) -> Self {
         Self {
             token_type,
-            lexeme,
+            source,
+            span,
             literal,
             line,
         }
     }
+
+    pub fn lexeme(&self) -> &str {
+        &self.source[self.span.start..self.span.end]
+    }
+
+    /// A token for the first `len` bytes of this one's lexeme, like the `+` of `+=`.
+    pub fn prefix(&self, token_type: TokenType, len: usize) -> Token {
+        let span = Span {
+            start: self.span.start,
+            end: self.span.start + len,
+        };
+        Self::spanned(token_type, self.source.clone(), span, None, self.line)
+    }
 }
 
-impl ToString for Token {
-    fn to_string(&self) -> String {
-        format!("{:?} {} {:?}", self.token_type, self.lexeme, self.literal)
+/// Tokens are equal if they read the same, wherever they came from.
+impl PartialEq for Token {
+    fn eq(&self, other: &Self) -> bool {
+        self.token_type == other.token_type
+            && self.lexeme() == other.lexeme()
+            && self.literal == other.literal
+            && self.line == other.line
+    }
+}
+
+impl std::fmt::Debug for Token {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("Token")
+            .field("token_type", &self.token_type)
+            .field("lexeme", &self.lexeme())
+            .field("literal", &self.literal)
+            .field("line", &self.line)
+            .finish()
+    }
+}
+
+impl Display for Token {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{:?} {} {:?}", self.token_type, self.lexeme(), self.literal)
     }
 }
 
 #[derive(Debug, Clone, PartialEq)]
 pub enum Literal {
-    String(String),
+    /// Shared with every value evaluated from it, so evaluating a string literal doesn't copy its text.
+    String(Rc<str>),
     Number(f64),
     True,
     False,
@@ -47,6 +99,7 @@ pub enum TokenType {
     Comma,
     Dot,
     Minus,
+    Percent,
     Plus,
     Semicolon,
     Slash,
@@ -61,6 +114,11 @@ pub enum TokenType {
     GreaterEqual,
     Less,
     LessEqual,
+    MinusEqual,
+    PercentEqual,
+    PlusEqual,
+    SlashEqual,
+    StarEqual,
 
     // Literals.
     Identifier,
@@ -69,6 +127,7 @@ pub enum TokenType {
 
     // Keywords.
     And,
+    Break,
     Class,
     Else,
     False,
@@ -92,26 +151,40 @@ impl TryFrom<&str> for TokenType {
     type Error = &'static str;
 
     fn try_from(value: &str) -> Result<Self, Self::Error> {
-        let token_type = match value {
-            "and" => TokenType::And,
-            "class" => TokenType::Class,
-            "else" => TokenType::Else,
-            "false" => TokenType::False,
-            "for" => TokenType::For,
-            "fun" => TokenType::Fun,
-            "if" => TokenType::If,
-            "nil" => TokenType::Nil,
-            "or" => TokenType::Or,
-            "print" => TokenType::Print,
-            "return" => TokenType::Return,
-            "super" => TokenType::Super,
-            "this" => TokenType::This,
-            "true" => TokenType::True,
-            "var" => TokenType::Var,
-            "while" => TokenType::While,
-            _ => return Err("Invalid keyworld"),
-        };
-
-        Ok(token_type)
+        keyword(value.as_bytes()).ok_or("Invalid keyworld")
     }
 }
+
+pub const KEYWORDS: &[&str] = &[
+    "and", "break", "class", "else", "false", "for", "fun", "if", "nil", "or", "print", "return", "super", "this",
+    "true", "var", "while",
+];
+
+/// The keyword spelled by `text`, if it is one.
+///
+/// Matching on the bytes lets the compiler branch on the length and then on one byte at a time, like the
+/// keyword trie in clox, instead of comparing `text` with every keyword in turn.
+pub fn keyword(text: &[u8]) -> Option<TokenType> {
+    let token_type = match text {
+        [b'i', b'f'] => TokenType::If,
+        [b'o', b'r'] => TokenType::Or,
+        [b'a', b'n', b'd'] => TokenType::And,
+        [b'f', b'o', b'r'] => TokenType::For,
+        [b'f', b'u', b'n'] => TokenType::Fun,
+        [b'n', b'i', b'l'] => TokenType::Nil,
+        [b'v', b'a', b'r'] => TokenType::Var,
+        [b'e', b'l', b's', b'e'] => TokenType::Else,
+        [b't', b'h', b'i', b's'] => TokenType::This,
+        [b't', b'r', b'u', b'e'] => TokenType::True,
+        [b'b', b'r', b'e', b'a', b'k'] => TokenType::Break,
+        [b'c', b'l', b'a', b's', b's'] => TokenType::Class,
+        [b'f', b'a', b'l', b's', b'e'] => TokenType::False,
+        [b'p', b'r', b'i', b'n', b't'] => TokenType::Print,
+        [b's', b'u', b'p', b'e', b'r'] => TokenType::Super,
+        [b'w', b'h', b'i', b'l', b'e'] => TokenType::While,
+        [b'r', b'e', b't', b'u', b'r', b'n'] => TokenType::Return,
+        _ => return None,
+    };
+
+    Some(token_type)
+}
@@ -0,0 +1,225 @@
+//! Golden-file tests for the `lox` binary.
+//!
+//! Each case runs the built binary and compares stdout, stderr and the exit code against
+//! `tests/golden/<name>.stdout`, `.stderr` and `.status`. Run with `UPDATE_GOLDEN=1` to rewrite them.
+
+use std::{
+    fs,
+    io::Write,
+    path::{Path, PathBuf},
+    process::{Command, Stdio},
+};
+
+fn golden_dir() -> PathBuf {
+    Path::new(env!("CARGO_MANIFEST_DIR")).join("tests").join("golden")
+}
+
+fn check(name: &str, args: &[&str], stdin: &str, current_dir: &Path) {
+    let mut child = Command::new(env!("CARGO_BIN_EXE_lox"))
+        .args(args)
+        .current_dir(current_dir)
+        .env_remove("RUST_BACKTRACE")
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .spawn()
+        .unwrap();
+    child.stdin.take().unwrap().write_all(stdin.as_bytes()).unwrap();
+    let output = child.wait_with_output().unwrap();
+
+    let actual = [
+        ("stdout", String::from_utf8(output.stdout).unwrap()),
+        ("stderr", String::from_utf8(output.stderr).unwrap()),
+        ("status", format!("{}\n", output.status.code().unwrap())),
+    ];
+
+    for (extension, actual) in actual {
+        let path = golden_dir().join(format!("{}.{}", name, extension));
+        if std::env::var_os("UPDATE_GOLDEN").is_some() {
+            fs::write(&path, &actual).unwrap();
+            continue;
+        }
+        let expected = fs::read_to_string(&path).unwrap_or_else(|_| panic!("missing {}", path.display()));
+        assert_eq!(actual, expected, "{} of {}", extension, name);
+    }
+}
+
+fn script(name: &str) -> String {
+    golden_dir().join(format!("{}.lox", name)).display().to_string()
+}
+
+#[test]
+fn test_file_mode() {
+    for name in ["arithmetic", "strings", "runtime_error", "parse_error", "print"] {
+        check(name, &[&script(name)], "", &golden_dir());
+    }
+}
+
+#[test]
+fn test_lang() {
+    check(
+        "runtime_error_ru",
+        &["--lang=ru", &script("runtime_error")],
+        "",
+        &golden_dir(),
+    );
+    // problems with the command line are translated too
+    check("missing_file_ru", &["--lang=ru", "missing.lox"], "", &golden_dir());
+    check("bad_option_ru", &["--lang=ru", "--buffering=some"], "", &golden_dir());
+    check(
+        "run_md_check_ru",
+        &["--lang=ru", "run-md", "--check", "notes.md"],
+        "",
+        &golden_dir(),
+    );
+}
+
+#[test]
+fn test_repl_piping() {
+    // a blank line is skipped, only the end of input ends the session
+    let input = "1 + 2\n\"a\" + \"b\"\n-nil\n\n1 + 1\n";
+    check("repl", &[], input, &golden_dir());
+    // buffering changes when output is flushed, never what is written
+    check("repl", &["--buffering=full"], input, &golden_dir());
+    check("repl", &["--buffering=none"], input, &golden_dir());
+
+    check(
+        "repl_variables",
+        &[],
+        "var a = 1;\na = a + 1;\na * 10\nb\n",
+        &golden_dir(),
+    );
+
+    check(
+        "repl_complete",
+        &[],
+        "var value = 1;\nvar total = 2;\n:complete va\n:complete t\n:complete x\n",
+        &golden_dir(),
+    );
+
+    // a statement left open carries on into the next line, blank lines included
+    check(
+        "repl_continuation",
+        &[],
+        "(1 +\n\n2)\nvar a =\n  3;\nprint\n  \"two\n lines\";\na *\n",
+        &golden_dir(),
+    );
+}
+
+#[test]
+fn test_stream() {
+    // the statement before the syntax error runs
+    check("stream", &["--stream", &script("stream")], "", &golden_dir());
+}
+
+#[test]
+fn test_tokens() {
+    check("tokens", &["--tokens", &script("print")], "", &golden_dir());
+    check("tokens_error", &["--tokens"], "1 0b2\n", &golden_dir());
+}
+
+#[test]
+fn test_ast() {
+    check("ast", &["--ast", &script("print")], "", &golden_dir());
+    check("ast_expression", &["--ast", &script("arithmetic")], "", &golden_dir());
+    check("ast_error", &["--ast", &script("parse_error")], "", &golden_dir());
+}
+
+#[test]
+fn test_eval() {
+    check("eval", &["-e", "1 + 2 * 3"], "", &golden_dir());
+    check(
+        "eval_program",
+        &["--precision=2", "--eval", "var a = 1 / 3; print a;"],
+        "",
+        &golden_dir(),
+    );
+    // only the REPL quotes strings
+    check("eval_string", &["-e", "\"a\" + \"b\""], "", &golden_dir());
+    check("eval_error", &["-e", "-\"a\""], "", &golden_dir());
+    // without code it's a usage error, like any other bad command line
+    check("usage", &["-e"], "", &golden_dir());
+    check("usage", &["--eval"], "", &golden_dir());
+}
+
+#[test]
+fn test_missing_file() {
+    check("missing_file", &["missing.lox"], "", &golden_dir());
+}
+
+#[test]
+fn test_usage() {
+    check("usage", &["one", "two", "three"], "", &golden_dir());
+}
+
+#[test]
+fn test_init_subcommand() {
+    let dir = std::env::temp_dir().join(format!("lox-cli-init-{}", std::process::id()));
+    let _ = fs::remove_dir_all(&dir);
+    fs::create_dir_all(&dir).unwrap();
+
+    check("init", &["init", "project"], "", &dir);
+    assert!(dir.join("project").join("main.lox").is_file());
+
+    fs::remove_dir_all(&dir).unwrap();
+}
+
+#[test]
+fn test_newline_terminators() {
+    check(
+        "repl_newline_terminators",
+        &["--newline-terminators"],
+        "var a = 1\na + 2\nprint a\n",
+        &golden_dir(),
+    );
+}
+
+#[test]
+fn test_strict() {
+    check(
+        "strict",
+        &["--strict"],
+        "var total = 0;\ntotl = total + 1\n",
+        &golden_dir(),
+    );
+}
+
+#[test]
+fn test_render_subcommand() {
+    let template = golden_dir().join("render.txt").display().to_string();
+    check("render", &["render", &template], "", &golden_dir());
+
+    // a failing tag is reported on stderr with the same status as a failing script
+    let dir = std::env::temp_dir().join(format!("lox-cli-render-{}", std::process::id()));
+    let _ = fs::remove_dir_all(&dir);
+    fs::create_dir_all(&dir).unwrap();
+    fs::write(dir.join("syntax.txt"), "a {{ 1 + }} b").unwrap();
+    fs::write(dir.join("runtime.txt"), "a\n{{ -\"a\" }} b").unwrap();
+    check("render_parse_error", &["render", "syntax.txt"], "", &dir);
+    check("render_runtime_error", &["render", "runtime.txt"], "", &dir);
+    check("render_missing", &["render", "missing.txt"], "", &dir);
+    fs::remove_dir_all(&dir).unwrap();
+}
+
+#[test]
+fn test_run_md_subcommand() {
+    check("run_md_check", &["run-md", "--check", "notes.md"], "", &golden_dir());
+    check("run_md_missing", &["run-md", "missing.md"], "", &golden_dir());
+
+    let dir = std::env::temp_dir().join(format!("lox-cli-run-md-{}", std::process::id()));
+    let _ = fs::remove_dir_all(&dir);
+    fs::create_dir_all(&dir).unwrap();
+    fs::copy(golden_dir().join("notes.md"), dir.join("notes.md")).unwrap();
+
+    check("run_md", &["run-md", "notes.md"], "", &dir);
+    let updated = fs::read_to_string(dir.join("notes.md")).unwrap();
+    assert!(updated.contains("```output\nUndefined variable 'missing'.\n[line 15]\n```"));
+    check("run_md", &["run-md", "--check", "notes.md"], "", &dir);
+
+    fs::remove_dir_all(&dir).unwrap();
+}
+
+#[test]
+fn test_precision() {
+    check("precision", &["--precision=3", &script("precision")], "", &golden_dir());
+}